@@ -36,7 +36,7 @@ fn let_match_impl(mut pat: Pat, expr: Expr) -> TokenStream {
     let ident_assign_somes = idents
         .iter()
         .map(|ident| {
-            let ident_underscore = Ident::new(&format!("{}_", ident.to_string()), Span::call_site());
+            let ident_underscore = Ident::new(&format!("{ident}_"), Span::call_site());
             quote! {
                 #ident = Some(#ident_underscore);
             }
@@ -76,7 +76,7 @@ fn find_identifiers(pat: &mut Pat) -> Vec<Ident> {
 fn find_identifiers_impl(pat: &mut Pat, ids: &mut Vec<Ident>) {
     match pat {
         Pat::Ident(pat) => {
-            let ident_underscore = format!("{}_", pat.ident.to_string());
+            let ident_underscore = format!("{}_", pat.ident);
             ids.push(std::mem::replace(&mut pat.ident, Ident::new(&ident_underscore, Span::call_site())));
         },
         Pat::Tuple(pat) => pat.elems.pairs_mut().for_each(|mut pair| find_identifiers_impl(pair.value_mut(), ids)),
@@ -41,7 +41,7 @@ fn let_match_tuplestruct() {
 
     let_match!(TestTupleStruct(x, y) = TestTupleStruct(4, true));
     assert_eq!(x, 4);
-    assert_eq!(y, true);
+    assert!(y);
 }
 
 #[test]
@@ -57,7 +57,7 @@ fn let_match_enum() {
 
     let_match!(TestEnum::TupleEnum2(x, y) = TestEnum::TupleEnum2(3, false));
     assert_eq!(x, 3);
-    assert_eq!(y, false);
+    assert!(!y);
 
     let_match!(TestEnum::StructEnum { x, y } = TestEnum::StructEnum { x: -3, y: 'a' });
     assert_eq!(x, -3);
@@ -97,7 +97,7 @@ fn let_match_slice() {
     assert_eq!(y, [2, 3, 4]);
     assert_eq!(z, 5);
 
-    let v = vec![1, 2, 3, 4, 5];
+    let v = [1, 2, 3, 4, 5];
     let_match!([x, y] = &v[1..3]);
     assert_eq!(*x, 2);
     assert_eq!(*y, 3);
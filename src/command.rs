@@ -0,0 +1,169 @@
+use crate::parse::constants::{Namespace, CONSTANTS};
+use crate::parse::{FractionForm, ParseOptions, Value, DEFAULT_MAX_CALL_DEPTH};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Settings {
+    pub parse: ParseOptions,
+    pub fractions: FractionForm,
+    /// Significant digits in decimal mode, or `None` for floats.
+    pub decimal_precision: Option<u64>,
+    pub max_call_depth: usize,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings { parse: ParseOptions::default(), fractions: FractionForm::default(), decimal_precision: None, max_call_depth: DEFAULT_MAX_CALL_DEPTH }
+    }
+}
+
+const MAX_PRECISION: u64 = 1000;
+
+const MAX_CALL_DEPTH: usize = 500;
+
+/// Runs a `:` command and returns the text to show.
+pub fn run(command: &str, settings: &mut Settings) -> String {
+    match command.split_whitespace().collect::<Vec<_>>()[..] {
+        [":constants"] => list_constants(),
+        [":strict"] => describe_strict(&settings.parse),
+        [":strict", "on"] => {
+            settings.parse.implicit_multiplication = false;
+            describe_strict(&settings.parse)
+        },
+        [":strict", "off"] => {
+            settings.parse.implicit_multiplication = true;
+            describe_strict(&settings.parse)
+        },
+        [":fractions"] => describe_fractions(settings.fractions),
+        [":fractions", "improper"] => {
+            settings.fractions = FractionForm::Improper;
+            describe_fractions(settings.fractions)
+        },
+        [":fractions", "mixed"] => {
+            settings.fractions = FractionForm::Mixed;
+            describe_fractions(settings.fractions)
+        },
+        [":precision"] => describe_precision(settings.decimal_precision),
+        [":precision", "off"] => {
+            settings.decimal_precision = None;
+            describe_precision(settings.decimal_precision)
+        },
+        [":precision", digits] => match digits.parse() {
+            Ok(precision @ 1..=MAX_PRECISION) => {
+                settings.decimal_precision = Some(precision);
+                describe_precision(settings.decimal_precision)
+            },
+            _ => format!("precision must be a whole number between 1 and {MAX_PRECISION}, or off"),
+        },
+        [":depth"] => describe_depth(settings.max_call_depth),
+        [":depth", depth] => match depth.parse() {
+            Ok(depth @ 1..=MAX_CALL_DEPTH) => {
+                settings.max_call_depth = depth;
+                describe_depth(settings.max_call_depth)
+            },
+            _ => format!("depth must be a whole number between 1 and {MAX_CALL_DEPTH}"),
+        },
+        _ => format!("unknown command \"{}\"", command.trim()),
+    }
+}
+
+fn describe_fractions(form: FractionForm) -> String {
+    match form {
+        FractionForm::Improper => String::from("fractions are shown as improper fractions, like 7/2"),
+        FractionForm::Mixed => String::from("fractions are shown as mixed numbers, like 3 1/2"),
+    }
+}
+
+fn describe_precision(precision: Option<u64>) -> String {
+    match precision {
+        Some(precision) => format!("decimal mode is on, with {precision} significant digits"),
+        None => String::from("decimal mode is off (numbers with a decimal point are floats)"),
+    }
+}
+
+fn describe_depth(depth: usize) -> String {
+    format!("functions may call each other up to {depth} levels deep")
+}
+
+fn describe_strict(options: &ParseOptions) -> String {
+    let state = if options.implicit_multiplication { "off (implicit multiplication allowed)" } else { "on (implicit multiplication disallowed)" };
+    format!("strict mode is {state}")
+}
+
+fn list_constants() -> String {
+    let mut lines = Vec::new();
+    for (namespace, heading) in [(Namespace::Math, "math:"), (Namespace::Physics, "physics:")] {
+        lines.push(String::from(heading));
+        for constant in CONSTANTS.iter().filter(|constant| constant.namespace == namespace) {
+            lines.push(format!("  {} = {}  ({})", constant.name, Value::Float(constant.value), constant.description));
+        }
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constants() {
+        let output = run(":constants", &mut Settings::default());
+        assert!(output.starts_with("math:\n  pi = 3.141592653589793  ("));
+        assert!(output.contains("\nphysics:\n"));
+        assert!(output.contains("\n  N_A = 6.02214076e23  (Avogadro constant (1 / mol))\n"));
+        assert_eq!(output.lines().count(), CONSTANTS.len() + 2);
+    }
+
+    #[test]
+    fn strict() {
+        let mut settings = Settings::default();
+        assert_eq!(run(":strict", &mut settings), "strict mode is off (implicit multiplication allowed)");
+        assert_eq!(run(":strict on", &mut settings), "strict mode is on (implicit multiplication disallowed)");
+        assert!(!settings.parse.implicit_multiplication);
+        assert_eq!(run(" :strict  off ", &mut settings), "strict mode is off (implicit multiplication allowed)");
+        assert!(settings.parse.implicit_multiplication);
+        assert_eq!(run(":strict maybe", &mut settings), "unknown command \":strict maybe\"");
+    }
+
+    #[test]
+    fn fractions() {
+        let mut settings = Settings::default();
+        assert_eq!(run(":fractions", &mut settings), "fractions are shown as improper fractions, like 7/2");
+        assert_eq!(run(":fractions mixed", &mut settings), "fractions are shown as mixed numbers, like 3 1/2");
+        assert_eq!(settings.fractions, FractionForm::Mixed);
+        assert_eq!(run(":fractions improper", &mut settings), "fractions are shown as improper fractions, like 7/2");
+        assert_eq!(settings.fractions, FractionForm::Improper);
+        assert_eq!(run(":fractions proper", &mut settings), "unknown command \":fractions proper\"");
+    }
+
+    #[test]
+    fn precision() {
+        let mut settings = Settings::default();
+        assert_eq!(run(":precision", &mut settings), "decimal mode is off (numbers with a decimal point are floats)");
+        assert_eq!(run(":precision 50", &mut settings), "decimal mode is on, with 50 significant digits");
+        assert_eq!(settings.decimal_precision, Some(50));
+        assert_eq!(run(":precision 0", &mut settings), "precision must be a whole number between 1 and 1000, or off");
+        assert_eq!(run(":precision 1001", &mut settings), "precision must be a whole number between 1 and 1000, or off");
+        assert_eq!(run(":precision many", &mut settings), "precision must be a whole number between 1 and 1000, or off");
+        assert_eq!(settings.decimal_precision, Some(50));
+        assert_eq!(run(":precision off", &mut settings), "decimal mode is off (numbers with a decimal point are floats)");
+        assert_eq!(settings.decimal_precision, None);
+    }
+
+    #[test]
+    fn depth() {
+        let mut settings = Settings::default();
+        assert_eq!(run(":depth", &mut settings), "functions may call each other up to 100 levels deep");
+        assert_eq!(run(":depth 250", &mut settings), "functions may call each other up to 250 levels deep");
+        assert_eq!(settings.max_call_depth, 250);
+        assert_eq!(run(":depth 0", &mut settings), "depth must be a whole number between 1 and 500");
+        assert_eq!(run(":depth 501", &mut settings), "depth must be a whole number between 1 and 500");
+        assert_eq!(run(":depth -1", &mut settings), "depth must be a whole number between 1 and 500");
+        assert_eq!(settings.max_call_depth, 250);
+        assert_eq!(run(":depth 250 300", &mut settings), "unknown command \":depth 250 300\"");
+    }
+
+    #[test]
+    fn unknown() {
+        assert_eq!(run(":nope", &mut Settings::default()), "unknown command \":nope\"");
+    }
+}
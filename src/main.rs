@@ -1,21 +1,53 @@
+pub mod command;
 pub mod parse;
 pub mod prompt;
 
+use command::Settings;
+use parse::Environment;
 use prompt::Prompter;
 
 fn main() {
+    let repl = std::thread::Builder::new().stack_size(parse::STACK_SIZE).spawn(run);
+    repl.expect("failed to start the REPL thread").join().unwrap();
+}
+
+fn run() {
     let mut prompter = Prompter::new();
+    let mut env = Environment::new();
+    let mut settings = Settings::default();
 
-    for input in prompter.lines() {
+    for (input_number, input) in prompter.lines() {
         if input.is_empty() {
             return;
         }
-        match parse::parse(&input) {
-            Ok(expr) => match expr.eval() {
-                Ok(val) => println!("{val}"),
-                Err(err) => println!("{err}"),
+        if input.starts_with(':') {
+            for line in command::run(&input, &mut settings).lines() {
+                print!("{line}\r\n");
+            }
+            env.set_decimal_precision(settings.decimal_precision);
+            env.set_max_call_depth(settings.max_call_depth);
+            continue;
+        }
+        let (statement, diagnostics) = parse::parse_recovering(&input, settings.parse);
+        for diagnostic in &diagnostics {
+            for line in diagnostic.render(&input).lines() {
+                print!("{line}\r\n");
+            }
+        }
+        if !diagnostics.is_empty() {
+            continue;
+        }
+        match statement.execute(&mut env) {
+            Ok(Some(val)) => {
+                println!("{}", val.display(settings.fractions));
+                env.record_result(input_number, val);
+            },
+            Ok(None) => (),
+            Err(err) => {
+                for line in err.render(&input).lines() {
+                    print!("{line}\r\n");
+                }
             },
-            Err(err) => println!("{err}"),
         }
     }
 }
@@ -1,170 +1,492 @@
+mod builtins;
+pub mod constants;
+mod decimal;
+pub mod diagnostic;
+pub mod environment;
 mod error;
 pub mod expr;
+mod operator;
+pub mod span;
+pub mod statement;
 pub mod token;
 
+use bigdecimal::BigDecimal;
+use num_bigint::BigInt;
+use num_traits::Num;
+
+pub use diagnostic::Diagnostic;
+pub use environment::{Environment, DEFAULT_MAX_CALL_DEPTH, MAX_EVAL_DEPTH, STACK_SIZE};
 use error::Error;
-pub use expr::{Expression, Value};
-use let_match::let_match;
+pub use expr::{Expression, ExpressionKind, FractionForm, Value};
+use operator::{Associativity, Fixity};
+use span::Span;
+pub use statement::Statement;
 use token::Token;
 
-#[derive(Debug)]
-enum Parse {
-    Tok(Token),
-    Expr(Expression),
-    Temp,
+#[derive(Debug, Clone, Copy)]
+pub struct ParseOptions {
+    /// Whether `2x` means `2 * x`. Strict mode turns this off.
+    pub implicit_multiplication: bool,
 }
 
-pub fn parse(expr: &str) -> Result<Expression, Error> {
-    parse_impl(token::tokenize(expr)?.into_iter().map(|tok| Parse::Tok(tok)).collect::<Vec<_>>())
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self { implicit_multiplication: true }
+    }
 }
 
-fn parse_impl(mut tokens: Vec<Parse>) -> Result<Expression, Error> {
-    parse_parens(&mut tokens)?;
-    parse_whole_numbers(&mut tokens);
-    parse_unary_ops(&mut tokens);
-    parse_sums_differences(&mut tokens);
-    trim_spaces(&mut tokens);
-    get_result(tokens)
+pub fn parse(input: &str) -> Result<Statement, Error> {
+    parse_with(input, ParseOptions::default())
 }
 
-fn get_result(mut tokens: Vec<Parse>) -> Result<Expression, Error> {
-    if tokens.len() == 1 {
-        if let Parse::Expr(expr) = tokens.pop().expect("just checked size") {
-            return Ok(expr);
-        }
+/// Returns only the first error.
+pub fn parse_with(input: &str, options: ParseOptions) -> Result<Statement, Error> {
+    let (statement, errors) = parse_all(input, options);
+    match errors.into_iter().next() {
+        Some(err) => Err(err),
+        None => Ok(statement),
     }
-    let mut message = None;
-    for i in 0..=tokens.len() {
-        match (tokens.get(i), &mut message) {
-            (Some(Parse::Temp), _) => panic!("Temps aren't allowed to persist"),
-            (Some(Parse::Tok(tok)), None) => message = Some(tok.to_str().to_string()),
-            (Some(Parse::Tok(tok)), Some(message)) => message.push_str(tok.to_str()),
-            (Some(Parse::Expr(_)) | None, Some(message)) => return Err(Error::SyntaxError(format!("invalid syntax \"{}\"", message.trim()))),
-            _ => (),
-        }
+}
+
+/// Reports every error, with [`ExpressionKind::Invalid`] wherever parsing failed.
+pub fn parse_recovering(input: &str, options: ParseOptions) -> (Statement, Vec<Diagnostic>) {
+    let (statement, errors) = parse_all(input, options);
+    let mut diagnostics: Vec<Diagnostic> = errors.into_iter().map(Diagnostic::from).collect();
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+    (statement, diagnostics)
+}
+
+fn parse_all(input: &str, options: ParseOptions) -> (Statement, Vec<Error>) {
+    let (tokens, errors) = token::tokenize_recovering(input);
+    let mut parser = Parser { tokens: &tokens, pos: 0, end_of_input: Span::new(input.len(), input.len()), options, errors };
+    let statement = parser.parse_input();
+    (statement, parser.errors)
+}
+
+const KEYWORDS: &[&str] = &["true", "false", "if", "then", "else", "xor"];
+
+fn invalid_syntax(tok: &Token, span: Span) -> Error {
+    Error::SyntaxError(format!("invalid syntax \"{}\"", tok.to_str()), span)
+}
+
+fn starts_operand(tok: &Token) -> bool {
+    match tok {
+        Token::Identifier(name) => !KEYWORDS.contains(&name.as_str()) || ["true", "false", "if"].contains(&name.as_str()),
+        Token::Operator(op) => op == "(" || operator::find_prefix(op).is_some(),
+        Token::Space => false,
+        _ => true,
     }
-    unreachable!();
 }
 
-fn trim_spaces(tokens: &mut Vec<Parse>) {
-    if matches!(tokens.last(), Some(Parse::Tok(Token::Space))) {
-        tokens.pop();
+fn is_separator(tok: &Token) -> bool {
+    matches!(tok, Token::Operator(op) if op == ";")
+}
+
+fn is_closer(tok: &Token) -> bool {
+    match tok {
+        Token::Operator(op) => [")", ",", ":", ";"].contains(&op.as_str()),
+        Token::Identifier(name) => name == "then" || name == "else",
+        _ => false,
     }
-    if matches!(tokens.first(), Some(Parse::Tok(Token::Space))) {
-        tokens.remove(0);
+}
+
+fn starts_implicit_factor(tok: &Token) -> bool {
+    match tok {
+        Token::Identifier(name) => !KEYWORDS.contains(&name.as_str()),
+        Token::PreviousResult(_) => true,
+        Token::Operator(op) => op == "(" || op == "√",
+        _ => false,
     }
 }
 
-fn parse_whole_numbers(tokens: &mut Vec<Parse>) {
-    for tok in tokens {
-        if let Parse::Tok(Token::WholeNumber(num)) = tok {
-            let num = num.parse().expect("num should always be a sequence of digits");
-            *tok = Parse::Expr(Expression::Constant(Value::Integer(num)))
-        }
+fn parse_whole_number(num: &str) -> ExpressionKind {
+    let digits = num.replace('_', "");
+    ExpressionKind::Constant(Value::from(digits.parse::<BigInt>().expect("whole numbers should only contain digits")))
+}
+
+fn parse_radix_number(num: &str, span: Span) -> Result<ExpressionKind, Error> {
+    let (radix, radix_name) = match &num[..2] {
+        "0x" | "0X" => (16, "hexadecimal"),
+        "0o" | "0O" => (8, "octal"),
+        _ => (2, "binary"),
+    };
+    let digits = num[2..].replace('_', "");
+    if digits.is_empty() {
+        return Err(Error::SyntaxError(format!("{radix_name} literal \"{num}\" has no digits"), span));
     }
+    if let Some(c) = digits.chars().find(|c| !c.is_digit(radix)) {
+        return Err(Error::SyntaxError(format!("invalid digit '{c}' in {radix_name} literal \"{num}\""), span));
+    }
+    Ok(ExpressionKind::Constant(Value::from(BigInt::from_str_radix(&digits, radix).expect("digits should be checked already"))))
 }
 
-fn parse_parens(tokens: &mut Vec<Parse>) -> Result<(), Error> {
-    let mut closing_paren_idx = None;
-    let mut n_nested_parens = 0u8;
-    for i in (0..tokens.len()).rev() {
-        match (&tokens[i], closing_paren_idx) {
-            (Parse::Tok(Token::Operator(op)), None) if op == ")" => closing_paren_idx = Some(i),
-            (Parse::Tok(Token::Operator(op)), None) if op == "(" => return Err(Error::SyntaxError(String::from("unmatched \"(\""))),
-            (Parse::Tok(Token::Operator(op)), Some(_)) if op == ")" => n_nested_parens += 1,
-            (Parse::Tok(Token::Operator(op)), Some(_)) if op == "(" && n_nested_parens > 0 => n_nested_parens -= 1,
-            (Parse::Tok(Token::Operator(op)), Some(closei)) if op == "(" && n_nested_parens == 0 => {
-                let mut removed = tokens.splice(i..=closei, [Parse::Temp]).skip(1).collect::<Vec<_>>();
-                removed.pop();
-                trim_spaces(&mut removed);
-                if removed.len() == 0 {
-                    return Err(Error::SyntaxError(String::from("empty parentheses")));
-                }
-                tokens[i] = Parse::Expr(parse_impl(removed)?);
-                closing_paren_idx = None;
+fn parse_previous_result(reference: &str, span: Span) -> Result<ExpressionKind, Error> {
+    match reference[1..].parse() {
+        Ok(input_number) => Ok(ExpressionKind::PreviousResult(input_number)),
+        Err(_) if reference.len() == 1 => Err(Error::SyntaxError(format!("\"{reference}\" must be followed by an input number"), span)),
+        Err(_) => Err(Error::SyntaxError(format!("input number \"{reference}\" is out of range"), span)),
+    }
+}
+
+fn parse_decimal(num: &str, span: Span) -> Result<ExpressionKind, Error> {
+    let digits = num.replace('_', "");
+    match (digits.parse(), digits.parse::<BigDecimal>()) {
+        (Ok(float), Ok(exact)) => Ok(ExpressionKind::DecimalLiteral(float, exact)),
+        (Ok(_), Err(_)) => Err(Error::SyntaxError(format!("number \"{num}\" is out of range"), span)),
+        (Err(_), _) if num.ends_with(['e', 'E', '+', '-']) => Err(Error::SyntaxError(format!("malformed number \"{num}\": exponent has no digits"), span)),
+        (Err(_), _) => Err(Error::SyntaxError(format!("malformed number \"{num}\""), span)),
+    }
+}
+
+struct Parser<'a> {
+    tokens: &'a [(Token, Span)],
+    pos: usize,
+    end_of_input: Span,
+    options: ParseOptions,
+    errors: Vec<Error>,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_spaces(&mut self) {
+        while matches!(self.tokens.get(self.pos), Some((Token::Space, _))) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<&'a Token> {
+        self.skip_spaces();
+        self.tokens.get(self.pos).map(|(tok, _)| tok)
+    }
+
+    fn peek_span(&mut self) -> Span {
+        self.skip_spaces();
+        self.tokens.get(self.pos).map_or(self.end_of_input, |(_, span)| *span)
+    }
+
+    fn is_postfix_here(&mut self, op: &str) -> bool {
+        self.skip_spaces();
+        let is_space = |pos: usize| matches!(self.tokens.get(pos), Some((Token::Space, _)));
+        let space_before = is_space(self.pos + 1);
+        let sign_pos = if space_before { self.pos + 2 } else { self.pos + 1 };
+        let Some((tok, sign_span)) = self.tokens.get(sign_pos) else { return true };
+        if !starts_operand(tok) {
+            return true;
+        }
+        let Token::Operator(sign) = tok else { return false };
+        if operator::find_infix(sign).is_none() {
+            return false;
+        }
+        let space_after = is_space(sign_pos + 1);
+        if !space_before && !space_after {
+            let span = self.tokens[self.pos].1.to(*sign_span);
+            self.error(Error::SyntaxError(format!("\"{op}\" before \"{sign}\" is ambiguous, so add a space after \"{sign}\" if it is an operator or before it if it is a sign"), span));
+        }
+        space_after || !space_before
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let tok = self.peek();
+        if tok.is_some() {
+            self.pos += 1;
+        }
+        tok
+    }
+
+    fn last_span(&self) -> Span {
+        self.tokens[..self.pos].iter().rev().find(|(tok, _)| !matches!(tok, Token::Space)).map_or(Span::new(0, 0), |(_, span)| *span)
+    }
+
+    fn next_if_operator(&mut self, symbol: &str) -> bool {
+        let is_match = matches!(self.peek(), Some(Token::Operator(op)) if op == symbol);
+        if is_match {
+            self.pos += 1;
+        }
+        is_match
+    }
+
+    fn next_if_adjacent_operator(&mut self, symbol: &str) -> bool {
+        let is_match = matches!(self.tokens.get(self.pos), Some((Token::Operator(op), _)) if op == symbol);
+        if is_match {
+            self.pos += 1;
+        }
+        is_match
+    }
+
+    fn error(&mut self, err: Error) {
+        if !self.errors.iter().any(|prev| prev.span() == err.span()) {
+            self.errors.push(err);
+        }
+    }
+
+    fn invalid(&mut self, err: Error) -> ExpressionKind {
+        self.error(err);
+        ExpressionKind::Invalid
+    }
+
+    fn expect(&mut self, expected: &str) {
+        let span = self.peek_span();
+        match self.peek() {
+            Some(Token::Operator(tok) | Token::Identifier(tok)) if tok == expected => {
+                self.next();
             },
-            _ => (),
+            Some(tok) => self.error(Error::SyntaxError(format!("expected \"{expected}\" but found \"{}\"", tok.to_str()), span)),
+            None => self.error(Error::SyntaxError(format!("expected \"{expected}\" but found end of input"), span)),
         }
     }
-    if closing_paren_idx.is_some() {
-        return Err(Error::SyntaxError(String::from("unmatched \")\"")));
+
+    fn prev_adjacent(&mut self) -> Option<&'a (Token, Span)> {
+        self.skip_spaces();
+        self.tokens[..self.pos].last()
     }
-    Ok(())
-}
 
-fn parse_unary_ops(tokens: &mut Vec<Parse>) {
-    let mut expr_idx = None;
-    for i in (0..tokens.len()).rev() {
-        match (&tokens[i], expr_idx) {
-            (Parse::Expr(_), _) => expr_idx = Some(i),
-            (Parse::Tok(Token::Operator(op)), Some(expri)) => {
-                if !(op == "+" || op == "-") {
-                    expr_idx = None;
-                    continue;
-                }
-                match &tokens[usize::saturating_sub(i, 2)..i] {
-                    [_, Parse::Tok(Token::Operator(_))] | [Parse::Tok(Token::Operator(_))] => expr_idx = None,
-                    [] | [Parse::Tok(Token::Space)] | [Parse::Tok(Token::Operator(_)), Parse::Tok(Token::Space)] => {
-                        let mut removed = tokens.splice(i..=expri, [Parse::Temp]).filter(|tok| !matches!(tok, Parse::Tok(Token::Space)));
-                        let_match!(Some(Parse::Tok(Token::Operator(op))) = removed.next());
-                        let_match!(Some(Parse::Expr(expr)) = removed.next());
-                        debug_assert!(removed.next().is_none(), "splice should have exactly 2 elements");
-                        drop(removed);
-
-                        let expr = Box::new(expr);
-                        tokens[i] = Parse::Expr(if op == "+" { Expression::UnaryPlus(expr) } else { Expression::UnaryMinus(expr) });
-                        expr_idx = Some(i);
+    fn parse_input(&mut self) -> Statement {
+        let mut statements = Vec::new();
+        loop {
+            while self.next_if_operator(";") {}
+            if self.peek().is_none() {
+                break;
+            }
+            statements.push(self.parse_statement());
+            self.skip_leftovers();
+        }
+        match <[Statement; 1]>::try_from(statements) {
+            Ok([statement]) => statement,
+            Err(statements) => Statement::Sequence(statements),
+        }
+    }
+
+    fn skip_leftovers(&mut self) {
+        while let Some(tok) = self.peek().filter(|tok| !is_separator(tok)) {
+            let span = self.peek_span();
+            match tok {
+                Token::Operator(op) if op == ")" => self.error(Error::SyntaxError(String::from("unmatched \")\""), span)),
+                tok => self.error(invalid_syntax(tok, span)),
+            }
+            // Parse what follows as another expression, to find any errors in it too.
+            if starts_operand(tok) {
+                self.parse_expr(0);
+            } else {
+                self.next();
+            }
+        }
+    }
+
+    fn parse_statement(&mut self) -> Statement {
+        let start = self.pos;
+        if let Some(Token::Identifier(name)) = self.next().filter(|tok| !KEYWORDS.contains(&tok.to_str())) {
+            // Don't skip spaces here, since parameters must directly follow the name.
+            let params_start = self.tokens.get(self.pos).map_or(self.end_of_input, |(_, span)| *span);
+            let params = self.parse_params();
+            let params_span = params_start.to(self.last_span());
+            if self.next_if_operator("=") {
+                let name = name.clone();
+                return match params {
+                    None => Statement::Assignment(name, self.parse_expr(0)),
+                    Some(params) => {
+                        if let Some(param) = params.iter().enumerate().find_map(|(i, param)| params[..i].contains(param).then_some(param)) {
+                            self.error(Error::SyntaxError(format!("duplicate parameter {param} in definition of {name}"), params_span));
+                        }
+                        Statement::FunctionDefinition(name, params, self.parse_expr(0))
                     },
-                    _ => (),
-                }
+                };
+            }
+        }
+        self.pos = start;
+        Statement::Expression(self.parse_expr(0))
+    }
+
+    fn parse_params(&mut self) -> Option<Vec<String>> {
+        let start = self.pos;
+        let params = self.parse_params_impl();
+        if params.is_none() {
+            self.pos = start;
+        }
+        params
+    }
+
+    fn parse_params_impl(&mut self) -> Option<Vec<String>> {
+        if !self.next_if_adjacent_operator("(") {
+            return None;
+        }
+        let mut params = Vec::new();
+        if self.next_if_operator(")") {
+            return Some(params);
+        }
+        loop {
+            let Some(Token::Identifier(param)) = self.next().filter(|tok| !KEYWORDS.contains(&tok.to_str())) else { return None };
+            params.push(param.clone());
+            match self.next() {
+                Some(Token::Operator(op)) if op == "," => (),
+                Some(Token::Operator(op)) if op == ")" => return Some(params),
+                _ => return None,
+            }
+        }
+    }
+
+    fn parse_args(&mut self) -> Vec<Expression> {
+        let open = self.last_span();
+        let mut args = Vec::new();
+        if self.next_if_operator(")") {
+            return args;
+        }
+        loop {
+            args.push(self.parse_expr(0));
+            if !self.end_group_element(open, true) {
+                return args;
+            }
+        }
+    }
+
+    fn end_group_element(&mut self, open: Span, commas_allowed: bool) -> bool {
+        let span = self.peek_span();
+        match self.peek() {
+            Some(Token::Operator(op)) if op == ")" => {
+                self.next();
+                false
+            },
+            Some(Token::Operator(op)) if op == "," && commas_allowed => {
+                self.next();
+                true
+            },
+            Some(tok) if !is_separator(tok) => {
+                self.error(invalid_syntax(tok, span));
+                self.next();
+                self.skip_to_group_end();
+                self.end_group_element(open, commas_allowed)
+            },
+            // The group was never closed, since it can't continue into the next statement.
+            _ => {
+                self.error(Error::SyntaxError(String::from("unmatched \"(\""), open));
+                false
             },
-            _ => (),
         }
     }
-}
 
-fn parse_sums_differences(tokens: &mut Vec<Parse>) {
-    let mut lhs_idx = None;
-    let mut found_op = false;
-    let mut i = 0;
-    while i < tokens.len() {
-        match (&tokens[i], lhs_idx, found_op) {
-            (Parse::Expr { .. }, _, false) => lhs_idx = Some(i),
-            (Parse::Tok(Token::Operator(op)), Some(_), false) => {
-                if op == "+" || op == "-" {
-                    found_op = true;
-                } else {
-                    lhs_idx = None;
+    fn skip_to_group_end(&mut self) {
+        let mut depth = 0;
+        while let Some(tok) = self.peek() {
+            match tok {
+                Token::Operator(op) if (op == ")" || op == ",") && depth == 0 => return,
+                tok if is_separator(tok) => return,
+                Token::Operator(op) if op == "(" => depth += 1,
+                Token::Operator(op) if op == ")" => depth -= 1,
+                _ => (),
+            }
+            self.next();
+        }
+    }
+
+    fn parse_expr(&mut self, min_binding_power: u8) -> Expression {
+        let mut lhs = self.parse_prefix();
+        while let Some(tok) = self.peek() {
+            let operator = match tok {
+                _ if self.options.implicit_multiplication && starts_implicit_factor(tok) => &operator::JUXTAPOSITION,
+                Token::Operator(op) | Token::Identifier(op) => {
+                    // `%` is infix when an operand follows it, and a sign only starts one when it hugs it, as in `7 % -3`.
+                    let postfix = operator::find_postfix(op).filter(|_| operator::find_infix(op).is_none() || self.is_postfix_here(op));
+                    let Some(operator) = postfix.or_else(|| operator::find_infix(op)) else { break };
+                    operator
+                },
+                _ => break,
+            };
+            if operator.binding_power < min_binding_power {
+                break;
+            }
+            if !operator.symbol.is_empty() {
+                self.next();
+            }
+            let lhs_span = lhs.span;
+            let kind = match operator.fixity {
+                Fixity::Infix(associativity, make_expr) => {
+                    let rhs_binding_power = if associativity == Associativity::Left { operator.binding_power + 1 } else { operator.binding_power };
+                    make_expr(Box::new(lhs), Box::new(self.parse_expr(rhs_binding_power)))
+                },
+                Fixity::Ternary(separator, make_expr) => {
+                    let middle = self.parse_expr(0);
+                    self.expect(separator);
+                    make_expr(Box::new(lhs), Box::new(middle), Box::new(self.parse_expr(operator.binding_power)))
+                },
+                Fixity::Postfix(make_expr) => make_expr(Box::new(lhs)),
+                Fixity::Prefix(_) => unreachable!("find_infix and find_postfix don't return prefix operators"),
+            };
+            lhs = Expression::new(kind, lhs_span.to(self.last_span()));
+        }
+        lhs
+    }
+
+    fn parse_prefix(&mut self) -> Expression {
+        let start = self.peek_span();
+        let start_pos = self.pos;
+        let kind = self.parse_prefix_kind();
+        let span = if self.pos == start_pos { start } else { start.to(self.last_span()) };
+        Expression::new(kind, span)
+    }
+
+    fn parse_prefix_kind(&mut self) -> ExpressionKind {
+        let prev = self.prev_adjacent();
+        let span = self.peek_span();
+        let tok = match self.peek() {
+            None => return self.invalid(Error::SyntaxError(String::from("unexpected end of input"), span)),
+            Some(tok) if is_closer(tok) => return self.invalid(Error::SyntaxError(format!("expected an operand before \"{}\"", tok.to_str()), span)),
+            Some(tok) => tok,
+        };
+        self.next();
+        let kind = match tok {
+            Token::WholeNumber(num) => Ok(parse_whole_number(num)),
+            Token::RadixNumber(num) => parse_radix_number(num, span),
+            Token::PreviousResult(reference) => parse_previous_result(reference, span),
+            Token::Decimal(num) => parse_decimal(num, span),
+            Token::Identifier(name) if name == "true" => Ok(ExpressionKind::Constant(Value::Bool(true))),
+            Token::Identifier(name) if name == "false" => Ok(ExpressionKind::Constant(Value::Bool(false))),
+            Token::Identifier(name) if name == "if" => Ok(self.parse_if()),
+            Token::Identifier(name) if !KEYWORDS.contains(&name.as_str()) && self.next_if_adjacent_operator("(") => Ok(ExpressionKind::Call(name.clone(), self.parse_args())),
+            Token::Identifier(name) if !KEYWORDS.contains(&name.as_str()) => Ok(ExpressionKind::Variable(name.clone())),
+            Token::Operator(op) if op == "(" => Ok(self.parse_parens()),
+            Token::Operator(op) if operator::find_prefix(op).is_some() => {
+                if let Some((Token::Operator(prev_op), prev_span)) = prev {
+                    if operator::is_sign(prev_op) && operator::is_sign(op) {
+                        self.error(Error::SyntaxError(format!("invalid syntax \"{prev_op}{op}\""), prev_span.to(span)));
+                    }
                 }
+                let operator = operator::find_prefix(op).expect("checked above");
+                let Fixity::Prefix(make_expr) = operator.fixity else { unreachable!("find_prefix only returns prefix operators") };
+                Ok(make_expr(Box::new(self.parse_expr(operator.binding_power))))
             },
-            (Parse::Tok(Token::Operator(_)), Some(_), true) => {
-                found_op = false;
-                lhs_idx = None;
-            },
-            (Parse::Expr { .. }, Some(lhsi), true) => {
-                let mut removed = tokens.splice(lhsi..=i, [Parse::Temp]).filter(|tok| !matches!(tok, Parse::Tok(Token::Space)));
-                let_match!(Some(Parse::Expr(lhs_expr)) = removed.next());
-                let_match!(Some(Parse::Tok(Token::Operator(op))) = removed.next());
-                let_match!(Some(Parse::Expr(rhs_expr)) = removed.next());
-                debug_assert!(removed.next().is_none(), "splice should have exactly 3 elements");
-                drop(removed);
-
-                let lhs = Box::new(lhs_expr);
-                let rhs = Box::new(rhs_expr);
-                tokens[lhsi] = Parse::Expr(if op == "+" { Expression::Sum(lhs, rhs) } else { Expression::Difference(lhs, rhs) });
-
-                i = lhsi;
-                found_op = false;
+            // Already reported by the tokenizer.
+            Token::Invalid(_) => Ok(ExpressionKind::Invalid),
+            tok => {
+                // Skip a stray token, like the `*` in `1 + * 2`, and look for the operand after it.
+                self.error(invalid_syntax(tok, span));
+                Ok(self.parse_prefix_kind())
             },
-            _ => (),
+        };
+        kind.unwrap_or_else(|err| self.invalid(err))
+    }
+
+    fn parse_if(&mut self) -> ExpressionKind {
+        let condition = self.parse_expr(0);
+        self.expect("then");
+        let if_true = self.parse_expr(0);
+        self.expect("else");
+        let if_false = self.parse_expr(0);
+        ExpressionKind::Conditional(Box::new(condition), Box::new(if_true), Box::new(if_false))
+    }
+
+    fn parse_parens(&mut self) -> ExpressionKind {
+        let open = self.last_span();
+        if self.next_if_operator(")") {
+            return self.invalid(Error::SyntaxError(String::from("empty parentheses"), open.to(self.last_span())));
         }
-        i += 1;
+        let expr = self.parse_expr(0);
+        self.end_group_element(open, false);
+        expr.kind
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use num_rational::BigRational;
     use std::fmt::Debug;
     use std::sync::Once;
 
@@ -176,21 +498,51 @@ mod tests {
     }
 
     fn expect_value(expected_output: Value, input: &str) {
+        expect_value_in(&mut Environment::new(), expected_output, input);
+    }
+
+    fn expect_value_in(env: &mut Environment, expected_output: Value, input: &str) {
         match parse(input) {
             Err(err) => fail_test(input, expected_output, err),
-            Ok(expr) => match expr.eval() {
-                Ok(output) if output == expected_output => return,
+            Ok(statement) => match statement.execute(env) {
+                Ok(Some(output)) if output == expected_output => (),
                 Ok(bad_output) => fail_test(input, expected_output, bad_output),
                 Err(err) => fail_test(input, expected_output, err),
             },
         }
     }
 
+    fn expect_definition_in(env: &mut Environment, input: &str) {
+        match parse(input) {
+            Err(err) => fail_test(input, "definition", err),
+            Ok(statement) => match statement.execute(env) {
+                Ok(None) => (),
+                Ok(Some(bad_output)) => fail_test(input, "definition", bad_output),
+                Err(err) => fail_test(input, "definition", err),
+            },
+        }
+    }
+
+    fn expect_eval_error(input: &str) {
+        expect_eval_error_in(&mut Environment::new(), input);
+    }
+
+    fn expect_eval_error_in(env: &mut Environment, input: &str) {
+        match parse(input) {
+            Err(err) => fail_test(input, "EvalError", err),
+            Ok(statement) => match statement.execute(env) {
+                Err(Error::EvalError(..)) => (),
+                Err(bad_err) => fail_test(input, "EvalError", bad_err),
+                Ok(bad_output) => fail_test(input, "EvalError", bad_output),
+            },
+        }
+    }
+
     fn expect_syntax_error(input: &str) {
         match parse(input) {
-            Err(Error::SyntaxError(_)) => return,
+            Err(Error::SyntaxError(..)) => (),
             Err(bad_err) => fail_test(input, "SyntaxError", bad_err),
-            Ok(expr) => fail_test(input, "SyntaxError", expr),
+            Ok(statement) => fail_test(input, "SyntaxError", statement),
         }
     }
 
@@ -200,6 +552,8 @@ mod tests {
         expect_syntax_error("()");
         expect_syntax_error("( )");
         expect_syntax_error("(() ( ) )");
+        expect_syntax_error("1 -> 2");
+        expect_syntax_error("7 // 2");
     }
 
     #[test]
@@ -293,4 +647,905 @@ mod tests {
         expect_value(Value::Integer(3), "- - + 3");
         expect_syntax_error("- --3");
     }
+
+    #[test]
+    fn parse_product() {
+        expect_value(Value::Integer(6), "2*3");
+        expect_value(Value::Integer(6), " 2 * 3 ");
+        expect_value(Value::Integer(-6), "-2 * 3");
+        expect_value(Value::Integer(6), "-2 * -3");
+        expect_value(Value::Integer(24), "2 * 3 * 4");
+        expect_value(Value::Integer(7), "1 + 2 * 3");
+        expect_value(Value::Integer(7), "2 * 3 + 1");
+        expect_value(Value::Integer(-5), "1 - 2 * 3");
+        expect_value(Value::Integer(9), "(1 + 2) * 3");
+        expect_syntax_error("2 * * 3");
+        expect_syntax_error("2 *");
+    }
+
+    #[test]
+    fn parse_quotient() {
+        expect_value(Value::Integer(2), "6/3");
+        expect_value(ratio(7, 3), "7 / 3");
+        expect_value(Value::Integer(-2), "-6 / 3");
+        expect_value(Value::Integer(1), "12 / 3 / 4");
+        expect_value(Value::Integer(8), "12 / 3 * 2");
+        expect_value(Value::Integer(3), "1 + 4 / 2");
+        expect_eval_error("1 / 0");
+        expect_eval_error("1 / (2 - 2)");
+        expect_syntax_error("/ 3");
+    }
+
+    #[test]
+    fn parse_remainder() {
+        expect_value(Value::Integer(1), "7%3");
+        expect_value(Value::Integer(-1), "-7 % 3");
+        expect_value(Value::Integer(0), "6 % 3");
+        expect_value(Value::Integer(1), "7 % 4 % 2");
+        expect_value(Value::Integer(2), "7 * 2 % 4");
+        expect_value(Value::Integer(4), "3 + 7 % 6");
+        expect_eval_error("7 % 0");
+    }
+
+    #[test]
+    fn parse_power() {
+        expect_value(Value::Integer(8), "2^3");
+        expect_value(Value::Integer(8), " 2 ^ 3 ");
+        expect_value(Value::Integer(8), "2**3");
+        expect_value(Value::Integer(8), "2 ** 3");
+        expect_value(Value::Integer(1), "5 ^ 0");
+        expect_value(Value::Integer(512), "2^3^2");
+        expect_value(Value::Integer(512), "2 ** 3 ^ 2");
+        expect_value(Value::Integer(64), "(2^3)^2");
+        expect_value(Value::Integer(-4), "-2^2");
+        expect_value(Value::Integer(4), "(-2)^2");
+        expect_value(Value::Integer(4), "- -2^2");
+        expect_value(Value::Integer(-8), "(-2) ^ 3");
+        expect_value(Value::Integer(1), "1 ^ -2");
+        expect_value(Value::Integer(18), "2 * 3^2");
+        expect_value(Value::Integer(13), "3^2 + 4");
+        expect_value(Value::Integer(3), "12 / 2^2");
+        expect_value(Value::Integer(-1), "-1 ^ - - 1");
+        expect_value(ratio(1, 2), "2 ^ -1");
+        expect_value(ratio(1, 4), "2 ^ -2");
+        expect_eval_error("0 ^ -1");
+        expect_value(big("18446744073709551616"), "2 ^ 64");
+        expect_syntax_error("2 ^");
+        expect_syntax_error("^ 2");
+        expect_syntax_error("2 ^ ^ 2");
+        expect_syntax_error("2 * * 2");
+    }
+
+    #[test]
+    fn parse_precedence() {
+        expect_value(Value::Integer(20), "1 + 2 * 3 ^ 2 - -1 % 3");
+        expect_value(Value::Integer(-63), "1 - 2 ^ 3 ^ 2 / 8");
+        expect_value(Value::Integer(45150), &(1..=300).map(|n| n.to_string()).collect::<Vec<_>>().join(" + "));
+    }
+
+    #[test]
+    fn parse_float() {
+        expect_value(Value::Float(2.75), "2.75");
+        expect_value(Value::Float(0.5), ".5");
+        expect_value(Value::Float(2.0), "2.");
+        expect_value(Value::Float(-0.5), "-.5");
+        expect_value(Value::Float(2.5), "1.5 + 1");
+        expect_value(Value::Float(0.5), "1 - .5");
+        expect_value(Value::Float(5.0), "2 * 2.5");
+        expect_value(Value::Float(2.5), "5 / 2.");
+        expect_value(Value::Float(1.5), "7.5 % 2");
+        expect_value(Value::Float(8.0), "2.0 ^ 3");
+        expect_value(Value::Float(2.0), "4 ^ .5");
+        expect_eval_error("1.5 / 0");
+        expect_eval_error("1 / 0.0");
+        expect_eval_error("1 % 0.0");
+        expect_eval_error("(-8) ^ .5");
+        expect_syntax_error("1.2.3");
+        expect_syntax_error(".");
+        expect_syntax_error("1 . 2");
+    }
+
+    #[test]
+    fn parse_scientific() {
+        expect_value(Value::Float(6.022e23), "6.022e23");
+        expect_value(Value::Float(1e-9), "1E-9");
+        expect_value(Value::Float(5000.0), "5e+3");
+        expect_value(Value::Float(2000.0), "2.e3");
+        expect_value(Value::Float(500.0), ".5e3");
+        expect_value(Value::Float(-1500.0), "-1.5e3");
+        expect_value(Value::Float(998.0), "1e3-2");
+        expect_value(Value::Float(1002.0), "1e3 - -2");
+        expect_value(Value::Float(2e-3), "2 * 1e-3");
+        expect_syntax_error("1e");
+        expect_syntax_error("1e+");
+        expect_syntax_error("1E-");
+        expect_syntax_error("1e + 2");
+        expect_syntax_error("2.5e");
+        expect_syntax_error("1e3e4");
+        expect_syntax_error("1e3x");
+    }
+
+    #[test]
+    fn parse_radix_number() {
+        expect_value(Value::Integer(255), "0xFF");
+        expect_value(Value::Integer(255), "0xff");
+        expect_value(Value::Integer(493), "0o755");
+        expect_value(Value::Integer(165), "0b1010_0101");
+        expect_value(Value::Integer(-16), "-0x10");
+        expect_value(Value::Integer(271), "0x10 + 0xFF");
+        expect_value(Value::Integer(i64::MAX), "0x7FFF_FFFF_FFFF_FFFF");
+        expect_syntax_error("0x");
+        expect_syntax_error("0b_");
+        expect_syntax_error("0b102");
+        expect_syntax_error("0o8");
+        expect_syntax_error("0xG");
+        expect_value(big("9223372036854775808"), "0x8000_0000_0000_0000");
+    }
+
+    #[test]
+    fn parse_digit_separator() {
+        expect_value(Value::Integer(1000000), "1_000_000");
+        expect_value(Value::Integer(1), "1_");
+        expect_value(Value::Float(3.5), "3.5_0");
+        expect_value(Value::Float(1e10), "1e1_0");
+        expect_syntax_error("1e_");
+    }
+
+    fn big(num: &str) -> Value {
+        Value::from(num.parse::<BigInt>().unwrap())
+    }
+
+    fn ratio(numer: i64, denom: i64) -> Value {
+        Value::Rational(BigRational::new(BigInt::from(numer), BigInt::from(denom)))
+    }
+
+    #[test]
+    fn parse_huge_literals() {
+        expect_value(Value::Integer(i64::MAX), "9223372036854775807");
+        expect_value(Value::Integer(-i64::MAX), "-9223372036854775807");
+        expect_value(big("9223372036854775808"), "9223372036854775808");
+        expect_value(big("99999999999999999999999"), "99_999_999_999_999_999_999_999");
+        expect_value(Value::Integer(i64::MIN), "-9223372036854775808");
+    }
+
+    #[test]
+    fn parse_rationals() {
+        let mut env = Environment::new();
+        expect_value(ratio(1, 2), "1/3 + 1/6");
+        expect_value(Value::Integer(1), "1/3 + 2/3");
+        expect_value(ratio(-1, 3), "-1/3");
+        expect_value(ratio(-1, 3), "1/-3");
+        expect_value(ratio(3, 4), "(1/2) / (2/3)");
+        expect_value(ratio(1, 6), "1/2 - 1/3");
+        expect_value(ratio(2, 9), "1/3 * 2/3");
+        expect_value(ratio(1, 6), "(7/2) % (2/3)");
+        expect_value(ratio(8, 27), "(2/3)^3");
+        expect_value(ratio(9, 4), "(2/3)^-2");
+        expect_value(Value::Float(0.75), "1/2 + 0.25");
+        expect_value(Value::Float(0.5), "(1/4) ^ 0.5");
+        expect_value(Value::Bool(true), "1/3 < 0.34");
+        expect_value(Value::Bool(true), "1/3 == 2/6");
+        expect_value(Value::Bool(true), "2^100 / 3 > 2^99 / 3 + 2^98 / 3");
+        expect_value(big("-9223372036854775808"), "-9223372036854775807 - 1");
+        expect_value(Value::Float(1.0 / 3.0), "decimal(1/3)");
+        expect_value(Value::Float(2.0), "decimal(2)");
+        expect_value(Value::Integer(3), "floor(7/2)");
+        expect_value(Value::Integer(-4), "floor(-7/2)");
+        expect_value(Value::Integer(4), "ceil(10/3)");
+        expect_value(Value::Integer(-4), "round(-7/2)");
+        expect_value(ratio(7, 2), "abs(-7/2)");
+        expect_value(ratio(1, 3), "min(1/2, 1/3)");
+        expect_value_in(&mut env, ratio(5, 6), "x = 1/2 + 1/3");
+        expect_value_in(&mut env, Value::Integer(5), "6x");
+        expect_eval_error("(1/2)!");
+        expect_eval_error("(1/2) & 1");
+        expect_eval_error("(1/2) / 0");
+        expect_eval_error("0^-1");
+        assert_eq!(ratio(7, 2).to_string(), "7/2");
+        assert_eq!(ratio(7, 2).display(FractionForm::Mixed), "3 1/2");
+        assert_eq!(ratio(-7, 2).display(FractionForm::Mixed), "-3 1/2");
+        assert_eq!(ratio(1, 2).display(FractionForm::Mixed), "1/2");
+        assert_eq!(Value::Integer(3).display(FractionForm::Mixed), "3");
+    }
+
+    fn dec(num: &str, precision: u64) -> Value {
+        Value::Decimal(num.parse().unwrap(), precision)
+    }
+
+    #[test]
+    fn parse_decimals() {
+        let mut env = Environment::new();
+        env.set_decimal_precision(Some(50));
+        expect_value_in(&mut env, dec("0.3", 50), "0.1 + 0.2");
+        expect_value_in(&mut env, Value::Bool(true), "0.1 + 0.2 == 0.3");
+        expect_value_in(&mut env, dec("0.33333333333333333333333333333333333333333333333333", 50), "1.0 / 3");
+        expect_value_in(&mut env, dec("0.66666666666666666666666666666666666666666666666667", 50), "2 / 3.0");
+        expect_value_in(&mut env, dec("0.33333333333333333333333333333333333333333333333333", 50), "1/3 + 0.0");
+        expect_value_in(&mut env, dec("0.33333333333333333333333333333333333333333333333333", 50), "decimal(1/3)");
+        expect_value_in(&mut env, dec("2", 50), "1.5 + 1/2");
+        expect_value_in(&mut env, dec("0.5", 50), "7.5 % 1");
+        expect_value_in(&mut env, dec("1.21", 50), "1.1^2");
+        expect_value_in(&mut env, dec("0.25", 50), "2.0^-2");
+        expect_value_in(&mut env, dec("-0.001", 50), "-1e-3");
+        expect_value_in(&mut env, dec("1.5e20", 50), "1.5e20");
+        expect_value_in(&mut env, dec("0.125", 50), "12.5%");
+        expect_value_in(&mut env, dec("0.5", 50), "50%");
+        expect_value_in(&mut env, dec("1.5", 50), "abs(-1.5)");
+        expect_value_in(&mut env, dec("1.5", 50), "max(1.5, 1/3)");
+        expect_value_in(&mut env, Value::Integer(2), "floor(2.5)");
+        expect_value_in(&mut env, Value::Integer(-3), "round(-2.5)");
+        expect_value_in(&mut env, Value::Bool(true), "0.1 < 1/3");
+        expect_value_in(&mut env, Value::Float(f64::INFINITY), "1.5 + inf");
+        expect_value_in(&mut env, dec("3.1415926535897932384626433832795028841971693993751", 50), "pi");
+        expect_value_in(&mut env, dec("6.2831853071795864769252867665590057683943387987502", 50), "tau");
+        expect_value_in(&mut env, dec("2.7182818284590452353602874713526624977572470937", 50), "e");
+        expect_value_in(&mut env, dec("2.7182818284590452353602874713526624977572470937", 50), "exp(1)");
+        expect_value_in(&mut env, dec("6.62607015e-34", 50), "h");
+        expect_value_in(&mut env, dec("1.4142135623730950488016887242096980785696718753769", 50), "sqrt(2)");
+        expect_value_in(&mut env, dec("1.4142135623730950488016887242096980785696718753769", 50), "2.0^0.5");
+        expect_value_in(&mut env, dec("1.4142135623730950488016887242096980785696718753769", 50), "2^(1/2)");
+        expect_value_in(&mut env, dec("0.57735026918962576450914878050195745564760175127013", 50), "(1/3)^(1/2)");
+        expect_value_in(&mut env, dec("2", 50), "4^(1/2)");
+        expect_value_in(&mut env, Value::Integer(8), "2^3");
+        expect_value_in(&mut env, ratio(4, 9), "(2/3)^2");
+        expect_value_in(&mut env, dec("0.69314718055994530941723212145817656807550013436026", 50), "ln(2)");
+        expect_value_in(&mut env, dec("0.84147098480789650665250232163029899962256306079837", 50), "sin(1)");
+        expect_value_in(&mut env, dec("3", 50), "log10(1000)");
+        expect_value_in(&mut env, dec("0.5", 50), "sin(pi / 6)");
+        expect_value_in(&mut env, dec("5", 50), "hypot(3, 4.0)");
+        expect_eval_error_in(&mut env, "sqrt(-1.0)");
+        expect_eval_error_in(&mut env, "ln(0)");
+        expect_eval_error_in(&mut env, "asin(1.5)");
+        expect_eval_error_in(&mut env, "(-8.0)^(1/3)");
+        expect_eval_error_in(&mut env, "0.0^-1");
+        expect_eval_error_in(&mut env, "1.5 / 0");
+        expect_eval_error_in(&mut env, "1 % 0.0");
+        expect_eval_error_in(&mut env, "1.5!");
+        expect_eval_error_in(&mut env, "1.5 & 1");
+        expect_eval_error_in(&mut env, "1e99999 * 1e99999");
+        expect_eval_error_in(&mut env, "exp(1e10)");
+        expect_eval_error_in(&mut env, "exp(-300000)");
+        expect_eval_error_in(&mut env, "sin(1e99999)");
+        expect_eval_error_in(&mut env, "atan2(1e-99999, 1e99999)");
+        expect_value_in(&mut env, dec("1e-99999", 50), "sin(1e-99999)");
+        expect_value_in(&mut env, dec("1", 50), "cos(1e-99999)");
+        expect_eval_error_in(&mut env, "(-8)^(1/3)");
+        env.set_decimal_precision(Some(5));
+        expect_value_in(&mut env, dec("0.33333", 5), "1.0 / 3");
+        expect_value_in(&mut env, dec("3.1416", 5), "pi");
+        env.set_decimal_precision(None);
+        expect_value_in(&mut env, Value::Float(0.30000000000000004), "0.1 + 0.2");
+        expect_value_in(&mut env, Value::Float(std::f64::consts::PI), "pi");
+        expect_value_in(&mut env, Value::Float(2f64.sqrt()), "2^(1/2)");
+        assert_eq!(dec("0.3", 50).to_string(), "0.3");
+        assert_eq!(dec("-1.5e20", 50).to_string(), "-1.5e20");
+        assert_eq!(dec("1e-7", 50).to_string(), "1e-7");
+        assert_eq!(dec("123.45", 50).to_string(), "123.45");
+    }
+
+    #[test]
+    fn parse_big_integers() {
+        expect_value(big("9223372036854775808"), "9223372036854775807 + 1");
+        expect_value(big("-9223372036854775809"), "-9223372036854775807 - 2");
+        expect_value(big("9223372036854775808"), "4611686018427387904 * 2");
+        expect_value(big("9223372036854775808"), "-(-9223372036854775807 - 1)");
+        expect_value(big("9223372036854775808"), "(-9223372036854775807 - 1) / -1");
+        expect_value(Value::Integer(i64::MIN), "-9223372036854775807 - 1");
+        expect_value(Value::Integer(0), "(-9223372036854775807 - 1) % -1");
+        expect_value(Value::Float(9223372036854775808.0), "9223372036854775807 + 1.0");
+        expect_value(big("1606938044258990275541962092341162602522202993782792835301376"), "2^200");
+        expect_value(Value::Integer(1), "2^200 - (2^200 - 1)");
+        expect_value(Value::Integer(1 << 62), "2^200 / 2^138");
+        expect_value(Value::Integer(3), "(2^200 + 3) % 2^100");
+        expect_value(big("-1267650600228229401496703205376"), "(-2)^101 / 2");
+        expect_value(Value::Bool(true), "2^100 > 2^99 + 2^98");
+        expect_value(Value::Bool(true), "2^64 == 2.0^64");
+        expect_value(Value::Bool(false), "2^64 + 1 == 2^64");
+        expect_value(Value::Bool(false), "2^53 + 1 == 2.0^53");
+        expect_value(Value::Bool(true), "2^53 + 1 > 2.0^53");
+        expect_value(Value::Bool(false), "2^3000 == inf");
+        expect_value(Value::Bool(true), "2^3000 < inf");
+        expect_value(Value::Bool(true), "-2^3000 > -inf");
+        expect_value(Value::Bool(false), "2^1024 == 2.0^1025");
+        expect_value(Value::Bool(false), "1/10 == 0.1");
+        expect_value(Value::Bool(true), "1/2 == 0.5");
+        expect_value(Value::Bool(false), "1 == inf - inf");
+        expect_value(Value::Bool(true), "max(2^1024, 1.0) == 2^1024");
+        expect_eval_error("sqrt(2^1100)");
+        expect_eval_error("ln(2^2000)");
+        expect_eval_error("atan2(1, 2^2000)");
+        expect_eval_error("decimal(2^2000)");
+        expect_value(big("1267650600228229401496703205377"), "max(2^100, 2^100 + 1, 3)");
+        expect_value(big("1267650600228229401496703205376"), "abs(-2^100)");
+        expect_value(big("100000000000000000000"), "round(1e20)");
+        expect_value(Value::Float(1.0), "2^100 / 2.0^100");
+        expect_value(Value::Float(0.5), "(2^100)^-1 * 2.0^99");
+        expect_value(big("1267650600228229401496703205375"), "2^100 - 1 | 1");
+        expect_value(big("2535301200456458802993406410752"), "2^100 << 1");
+        expect_value(Value::Integer(1), "2^100 >> 60 >> 40 & 1");
+        expect_value(big("-1267650600228229401496703205377"), "~2^100");
+        expect_value(Value::Integer(1), "(-1)^(2^100)");
+        expect_value(Value::Integer(0), "0^(2^100)");
+        expect_eval_error("9^9^9");
+        expect_eval_error("2^(2^100)");
+        expect_eval_error("1000000!");
+        expect_eval_error("(2^100)!");
+        expect_eval_error("1 << 2^100");
+        expect_eval_error("2^100 / 0");
+        expect_eval_error("2^100 + true");
+    }
+
+    #[test]
+    fn parse_variables() {
+        let mut env = Environment::new();
+        expect_value_in(&mut env, Value::Integer(12), "x = 3 * 4");
+        expect_value_in(&mut env, Value::Integer(12), "x");
+        expect_value_in(&mut env, Value::Integer(13), "x + 1");
+        expect_value_in(&mut env, Value::Integer(-24), " y2 = -2 * x ");
+        expect_value_in(&mut env, Value::Integer(-12), "x + y2");
+        expect_value_in(&mut env, Value::Integer(13), "x = x + 1");
+        expect_value_in(&mut env, Value::Integer(13), "x");
+        expect_value_in(&mut env, Value::Float(0.5), "x = .5");
+        expect_eval_error("x");
+        expect_eval_error("x = y");
+        expect_syntax_error("x =");
+        expect_syntax_error("= 3");
+        expect_syntax_error("3 = 4");
+        expect_syntax_error("x + 1 = 4");
+        expect_syntax_error("(x) = 4");
+        expect_syntax_error("x = y = 4");
+    }
+
+    #[test]
+    fn parse_functions() {
+        let mut env = Environment::new();
+        expect_definition_in(&mut env, "f(x, y) = x^2 + y");
+        expect_value_in(&mut env, Value::Integer(13), "f(3, 4)");
+        expect_value_in(&mut env, Value::Integer(13), " f( 3 ,4 ) ");
+        expect_value_in(&mut env, Value::Integer(-12), "1 - f(1 + 2, 4) - 0");
+        expect_value_in(&mut env, Value::Integer(170), "f(f(3, 4), 1)");
+        expect_definition_in(&mut env, "g(x) = f(x, x) * 2");
+        expect_value_in(&mut env, Value::Integer(12), "g(2)");
+        expect_definition_in(&mut env, "answer() = 42");
+        expect_value_in(&mut env, Value::Integer(42), "answer()");
+        expect_value_in(&mut env, Value::Integer(5), "x = 5");
+        expect_definition_in(&mut env, "h(y) = x + y");
+        expect_value_in(&mut env, Value::Integer(6), "h(1)");
+        expect_value_in(&mut env, Value::Integer(2), "f(1, 1)");
+        expect_value_in(&mut env, Value::Integer(5), "x");
+        expect_value_in(&mut env, Value::Integer(8), "f = 8");
+        expect_value_in(&mut env, Value::Integer(11), "f(1, 2) + f");
+        expect_eval_error_in(&mut env, "f(1)");
+        expect_eval_error_in(&mut env, "f(1, 2, 3)");
+        expect_eval_error_in(&mut env, "answer(1)");
+        expect_eval_error_in(&mut env, "y");
+        expect_eval_error_in(&mut env, "undefined(1)");
+        expect_syntax_error("f(x, x) = x");
+        expect_syntax_error("f(x, 1) = x");
+        expect_syntax_error("f(x,) = x");
+        expect_syntax_error("f(1,)");
+        expect_syntax_error("f(1");
+        expect_syntax_error("f(1, 2");
+        expect_syntax_error("f (x) = x");
+        expect_syntax_error("f(x) =");
+    }
+
+    #[test]
+    fn parse_recursive_functions() {
+        let mut env = Environment::new();
+        expect_definition_in(&mut env, "f(x) = f(x)");
+        expect_eval_error_in(&mut env, "f(1)");
+        expect_definition_in(&mut env, "g(x) = 1 + g(x - 1)");
+        expect_eval_error_in(&mut env, "g(1)");
+        env.set_max_call_depth(3);
+        expect_definition_in(&mut env, "h(x) = x + 1");
+        expect_definition_in(&mut env, "i(x) = h(h(x))");
+        expect_value_in(&mut env, Value::Integer(3), "i(1)");
+        expect_value_in(&mut env, Value::Integer(5), "i(i(1))");
+        expect_definition_in(&mut env, "j(x) = i(x) + 1");
+        expect_value_in(&mut env, Value::Integer(4), "j(1)");
+        expect_definition_in(&mut env, "k(x) = j(x) + 1");
+        expect_eval_error_in(&mut env, "k(1)");
+    }
+
+    #[test]
+    fn parse_deep_recursion() {
+        let nested = std::thread::Builder::new().stack_size(STACK_SIZE).spawn(|| {
+            let mut env = Environment::new();
+            env.set_max_call_depth(500);
+            let depth = MAX_EVAL_DEPTH / 500;
+            expect_definition_in(&mut env, &format!("g(x) = x <= 0 ? 0 : {}g(x - 1){}", "0 + (".repeat(depth), ")".repeat(depth)));
+            expect_value_in(&mut env, Value::Integer(0), "g(10)");
+            let result = parse("g(500)").unwrap().execute(&mut env);
+            assert!(matches!(result, Err(Error::EvalError(message, _)) if message.contains("nested")));
+            expect_value_in(&mut env, Value::Integer(0), "g(10)");
+        });
+        nested.unwrap().join().unwrap();
+    }
+
+    #[test]
+    fn parse_builtins() {
+        expect_value(Value::Float(4.0), "sqrt(16)");
+        expect_value(Value::Float(1.5), "sqrt(2.25)");
+        expect_value(Value::Integer(3), "abs(-3)");
+        expect_value(Value::Float(2.5), "abs(-2.5)");
+        expect_value(Value::Float(1.0), "exp(0)");
+        expect_value(Value::Float(0.0), "ln(1)");
+        expect_value(Value::Float(3.0), "log10(1000)");
+        expect_value(Value::Float(3.0), "log2(8)");
+        expect_value(Value::Float(0.0), "sin(0)");
+        expect_value(Value::Float(1.0), "cos(0)");
+        expect_value(Value::Float(0.0), "tan(0)");
+        expect_value(Value::Float(0.0), "asin(0)");
+        expect_value(Value::Float(0.0), "acos(1)");
+        expect_value(Value::Float(0.0), "atan(0)");
+        expect_value(Value::Float(0.0), "atan2(0, 1)");
+        expect_value(Value::Integer(2), "floor(2.7)");
+        expect_value(Value::Integer(-3), "floor(-2.5)");
+        expect_value(Value::Integer(3), "ceil(2.1)");
+        expect_value(Value::Integer(-3), "round(-2.5)");
+        expect_value(Value::Integer(7), "round(7)");
+        expect_value(Value::Float(1.5), "min(3, 1.5, 2)");
+        expect_value(Value::Integer(2), "max(1, 2)");
+        expect_value(Value::Integer(-4), "-max(-5, 4)");
+        expect_value(Value::Float(5.0), "hypot(3, 4)");
+        expect_value(Value::Float(6.0), "2 * sqrt(abs(-9))");
+        expect_eval_error("sqrt(-1)");
+        expect_eval_error("ln(0)");
+        expect_eval_error("ln(-1)");
+        expect_eval_error("asin(2)");
+        expect_eval_error("acos(-1.5)");
+        expect_eval_error("exp(1000)");
+        expect_eval_error("sqrt()");
+        expect_eval_error("sqrt(1, 2)");
+        expect_eval_error("atan2(1)");
+        expect_eval_error("min()");
+        expect_eval_error("sqrt");
+
+        let mut env = Environment::new();
+        expect_definition_in(&mut env, "sqrt(x) = x");
+        expect_value_in(&mut env, Value::Integer(-1), "sqrt(-1)");
+    }
+
+    #[test]
+    fn parse_constants() {
+        expect_value(Value::Float(std::f64::consts::PI), "pi");
+        expect_value(Value::Float(std::f64::consts::E), "e");
+        expect_value(Value::Float(std::f64::consts::TAU), "tau");
+        expect_value(Value::Float(std::f64::consts::TAU), "2 * pi");
+        expect_value(Value::Float(1.618033988749895), "phi");
+        expect_value(Value::Float(f64::INFINITY), "inf");
+        expect_value(Value::Float(f64::NEG_INFINITY), "-inf");
+        expect_value(Value::Float(299792458.0), "c");
+        expect_value(Value::Float(6.02214076e23), "N_A");
+        expect_value(Value::Float(1.380649e-23), "k_B");
+        expect_value(Value::Float(0.0), "sin(0 * pi)");
+
+        let mut env = Environment::new();
+        expect_eval_error_in(&mut env, "pi = 3");
+        expect_eval_error_in(&mut env, "e = 1");
+        expect_value_in(&mut env, Value::Float(std::f64::consts::PI), "pi");
+        expect_value_in(&mut env, Value::Float(9.80665), "g_0");
+        expect_value_in(&mut env, Value::Integer(3), "c = 3");
+        expect_value_in(&mut env, Value::Integer(6), "2c");
+        expect_definition_in(&mut env, "f(e) = e + 1");
+        expect_value_in(&mut env, Value::Integer(2), "f(1)");
+        expect_value_in(&mut env, Value::Float(std::f64::consts::E), "e");
+        expect_definition_in(&mut env, "c(x) = x");
+        expect_value_in(&mut env, Value::Integer(3), "c(3)");
+    }
+
+    #[test]
+    fn parse_previous_results() {
+        let mut env = Environment::new();
+        assert!(matches!(parse("ans").unwrap().execute(&mut env), Err(Error::EvalError(message, _)) if message == "ans has no result yet"));
+        expect_eval_error_in(&mut env, "$1");
+        env.record_result(1, Value::Integer(5));
+        env.record_result(2, Value::Integer(7));
+        expect_value_in(&mut env, Value::Integer(7), "ans");
+        expect_value_in(&mut env, Value::Integer(5), "$1");
+        expect_value_in(&mut env, Value::Integer(5), "_1");
+        expect_value_in(&mut env, Value::Integer(12), "$1 + _2");
+        expect_value_in(&mut env, Value::Integer(14), "ans * 2");
+        env.record_result(4, Value::Float(0.5));
+        expect_value_in(&mut env, Value::Float(0.5), "ans");
+        expect_value_in(&mut env, Value::Float(0.5), "$4");
+        expect_eval_error_in(&mut env, "$3");
+        expect_eval_error_in(&mut env, "ans = 1");
+        expect_definition_in(&mut env, "f(ans) = ans + 1");
+        expect_value_in(&mut env, Value::Integer(2), "f(1)");
+        expect_syntax_error("$");
+        expect_syntax_error("_");
+        expect_syntax_error("$ 1");
+        expect_syntax_error("$99999999999999999999999");
+        expect_syntax_error("$1 = 3");
+    }
+
+    #[test]
+    fn parse_comparisons() {
+        expect_value(Value::Bool(true), "1 == 1");
+        expect_value(Value::Bool(true), "1 == 1.0");
+        expect_value(Value::Bool(false), "1 != 1.0");
+        expect_value(Value::Bool(true), "1 < 2");
+        expect_value(Value::Bool(false), "2 < 2");
+        expect_value(Value::Bool(true), "2 <= 2");
+        expect_value(Value::Bool(true), "2.5 > 2");
+        expect_value(Value::Bool(false), "2 >= 2.5");
+        expect_value(Value::Bool(true), "1 + 1 == 2");
+        expect_value(Value::Bool(true), "2 * 3 > 5 - 1");
+        expect_value(Value::Bool(false), "inf < inf");
+        expect_value(Value::Bool(true), "true == true");
+        expect_value(Value::Bool(true), "true != false");
+        expect_value(Value::Bool(true), "1 < 2 == true");
+        expect_eval_error("true < false");
+        expect_eval_error("true == 1");
+        expect_eval_error("1 < 2 < 3");
+        expect_syntax_error("1 <");
+        expect_syntax_error("1 = = 1");
+        expect_syntax_error("1 < = 1");
+    }
+
+    #[test]
+    fn parse_booleans() {
+        expect_value(Value::Bool(true), "true");
+        expect_value(Value::Bool(false), "false");
+        expect_value(Value::Bool(false), "!true");
+        expect_value(Value::Bool(true), "!!true");
+        expect_value(Value::Bool(true), "!(1 > 2)");
+        expect_value(Value::Bool(true), "true && true");
+        expect_value(Value::Bool(false), "true && false");
+        expect_value(Value::Bool(true), "false || true");
+        expect_value(Value::Bool(false), "false || false");
+        expect_value(Value::Bool(true), "true || false && false");
+        expect_value(Value::Bool(true), "1 < 2 && 2 < 3");
+        expect_value(Value::Bool(true), "false && undefined || true");
+        expect_value(Value::Bool(true), "true || 1");
+        expect_eval_error("true + 1");
+        expect_eval_error("1 - false");
+        expect_eval_error("true * 1.5");
+        expect_eval_error("true / 1");
+        expect_eval_error("true % 1");
+        expect_eval_error("true ^ 2");
+        expect_eval_error("-true");
+        expect_eval_error("+false");
+        expect_eval_error("!1");
+        expect_eval_error("1 && true");
+        expect_eval_error("true && 1");
+        expect_eval_error("sqrt(true)");
+        expect_eval_error("max(1, false)");
+        expect_syntax_error("true = 1");
+        expect_syntax_error("f(true) = 1");
+
+        let mut env = Environment::new();
+        expect_value_in(&mut env, Value::Bool(true), "x = 1 < 2");
+        expect_value_in(&mut env, Value::Bool(false), "!x");
+        expect_definition_in(&mut env, "between(x, a, b) = a <= x && x <= b");
+        expect_value_in(&mut env, Value::Bool(true), "between(2, 1, 3)");
+        expect_value_in(&mut env, Value::Bool(false), "between(4, 1, 3)");
+    }
+
+    #[test]
+    fn parse_conditionals() {
+        expect_value(Value::Integer(1), "if true then 1 else 2");
+        expect_value(Value::Integer(2), "if false then 1 else 2");
+        expect_value(Value::Integer(2), "if 1 > 2 then 1 else 1 + 1");
+        expect_value(Value::Integer(11), "10 + if 1 < 2 then 1 else 2");
+        expect_value(Value::Integer(3), "if false then 1 else if false then 2 else 3");
+        expect_value(Value::Integer(2), "if true then if false then 1 else 2 else 3");
+        expect_value(Value::Integer(1), "true ? 1 : 2");
+        expect_value(Value::Integer(2), "false ? 1 : 2");
+        expect_value(Value::Integer(4), "1 > 2 ? 3 : 2 + 2");
+        expect_value(Value::Integer(3), "false ? 1 : false ? 2 : 3");
+        expect_value(Value::Integer(2), "true ? false ? 1 : 2 : 3");
+        expect_value(Value::Integer(1), "true || false ? 1 : 2");
+        expect_value(Value::Integer(1), "if true then 1 else undefined");
+        expect_value(Value::Integer(1), "false ? 1 / 0 : 1");
+        expect_eval_error("if 1 then 2 else 3");
+        expect_eval_error("1 ? 2 : 3");
+        expect_syntax_error("if true then 1");
+        expect_syntax_error("if true 1 else 2");
+        expect_syntax_error("if true then 1 else");
+        expect_syntax_error("true ? 1");
+        expect_syntax_error("true ? 1 2");
+        expect_syntax_error("1 : 2");
+        expect_syntax_error("then");
+        expect_syntax_error("if = 1");
+        expect_syntax_error("else(x) = 1");
+    }
+
+    #[test]
+    fn parse_recursive_conditionals() {
+        let mut env = Environment::new();
+        expect_definition_in(&mut env, "fact(n) = if n <= 1 then 1 else n * fact(n - 1)");
+        expect_value_in(&mut env, Value::Integer(1), "fact(0)");
+        expect_value_in(&mut env, Value::Integer(120), "fact(5)");
+        expect_value_in(&mut env, Value::Integer(2432902008176640000), "fact(20)");
+        expect_definition_in(&mut env, "fib(n) = n < 2 ? n : fib(n - 1) + fib(n - 2)");
+        expect_value_in(&mut env, Value::Integer(55), "fib(10)");
+        expect_definition_in(&mut env, "sign(x) = if x < 0 then -1 else if x == 0 then 0 else 1");
+        expect_value_in(&mut env, Value::Integer(-1), "sign(-5)");
+        expect_value_in(&mut env, Value::Integer(0), "sign(0)");
+        expect_value_in(&mut env, Value::Integer(1), "sign(2.5)");
+        expect_definition_in(&mut env, "count(n) = if n == 0 then 0 else 1 + count(n - 1)");
+        expect_value_in(&mut env, Value::Integer(50), "count(50)");
+        expect_eval_error_in(&mut env, "count(1000)");
+    }
+
+    #[test]
+    fn parse_bitwise() {
+        expect_value(Value::Integer(0b1000), "0b1100 & 0b1010");
+        expect_value(Value::Integer(0b1110), "0b1100 | 0b1010");
+        expect_value(Value::Integer(0b0110), "0b1100 xor 0b1010");
+        expect_value(Value::Integer(-1), "~0");
+        expect_value(Value::Integer(0xF0), "~0xF & 0xFF");
+        expect_value(Value::Integer(16), "1 << 4");
+        expect_value(Value::Integer(4), "16 >> 2");
+        expect_value(Value::Integer(-4), "-16 >> 2");
+        expect_value(big("9223372036854775808"), "1 << 63");
+        expect_value(Value::Integer(i64::MIN), "-1 << 63");
+        expect_value(big("18446744073709551616"), "1 << 64");
+        expect_value(big("18446744073709551616"), "2^62 << 2");
+        expect_value(big("36893488147419103232"), "2^63 << 2");
+        expect_value(Value::Integer(0), "1 >> 64");
+        expect_value(Value::Integer(-1), "-1 >> 2^100");
+        expect_value(Value::Integer(-1), "-1 >> 64");
+        expect_value(Value::Integer(0), "2^100 >> 200");
+        expect_value(Value::Integer(-1), "-(2^100) >> 200");
+        expect_value(Value::Integer(0), "2^100 >> 2^100");
+        expect_value(Value::Integer(1), "2^100 >> 100");
+        expect_value(Value::Integer(-2), "-(2^100 + 1) >> 100");
+        expect_value(Value::Integer(0), "0 << 2^100");
+        expect_value(Value::Integer(8), "1 << 2 + 1");
+        expect_value(Value::Integer(0xF0F), "0xF00 | 0xF0 & 0xF | 0xF");
+        expect_value(Value::Integer(1), "1 | 2 xor 3 & 6");
+        expect_value(Value::Integer(7), "(1 | 2) xor (3 & 6) xor 6");
+        expect_value(Value::Integer(3), "1 | 1 << 1");
+        expect_value(Value::Bool(true), "6 & 3 == 2");
+        expect_value(Value::Integer(0x30), "(0xFF & ~0xF) & 0x3F");
+        expect_eval_error("1 << -1");
+        expect_eval_error("1 << 2^20");
+        expect_eval_error("1.0 & 1");
+        expect_eval_error("1 | 0.5");
+        expect_eval_error("true xor false");
+        expect_eval_error("1 << 1.0");
+        expect_eval_error("~1.5");
+        expect_eval_error("~true");
+        expect_syntax_error("xor 1");
+        expect_syntax_error("1 xor");
+        expect_syntax_error("xor = 1");
+        expect_syntax_error("1 & & 2");
+    }
+
+    #[test]
+    fn parse_factorial() {
+        expect_value(Value::Integer(1), "0!");
+        expect_value(Value::Integer(1), "1!");
+        expect_value(Value::Integer(120), "5!");
+        expect_value(Value::Integer(120), "5 !");
+        expect_value(Value::Integer(2432902008176640000), "20!");
+        expect_value(Value::Integer(720), "3!!");
+        expect_value(Value::Integer(-6), "-3!");
+        expect_value(Value::Integer(64), "2^3!");
+        expect_value(Value::Integer(36), "3!^2");
+        expect_value(Value::Integer(7), "3! + 1");
+        expect_value(Value::Integer(24), "(2 + 2)!");
+        expect_value(Value::Bool(true), "3! == 6");
+        expect_value(Value::Bool(false), "!true");
+        expect_value(big("51090942171709440000"), "21!");
+        expect_value(big("93326215443944152681699238856266700490715968264381621468592963895217599993229915608941463976156518286253697920827223758251185210916864000000000000000000000000"), "100!");
+        expect_eval_error("(-1)!");
+        expect_eval_error("2.5!");
+        expect_eval_error("true!");
+        expect_syntax_error("!");
+        expect_syntax_error("3! 4");
+    }
+
+    #[test]
+    fn parse_percent() {
+        expect_value(ratio(1, 2), "50%");
+        expect_value(Value::Integer(30), "200 * 15%");
+        expect_value(Value::Integer(30), "200 * 15 %");
+        expect_value(ratio(3, 10), "3 * 10%");
+        expect_value(ratio(3, 2), "150%");
+        expect_value(Value::Float(0.025), "2.5%");
+        expect_value(ratio(3, 10), "(10 + 20)%");
+        expect_value(ratio(3, 4), "1 - 25%");
+        expect_value(Value::Integer(1), "7 % 3");
+        expect_value(Value::Integer(1), "7 % (+3)");
+        expect_value(Value::Integer(1), "7 % +3");
+        expect_value(Value::Integer(1), "7 % -3");
+        expect_value(ratio(23, 20), "15%+ 1");
+        expect_value(ratio(23, 20), "15% + 1");
+        expect_value(Value::Integer(31), "200 * 15% + 1");
+        expect_value(ratio(-19, 2), "50% - 10");
+        expect_value(ratio(1979, 20), "100 - 5% - 1");
+        expect_diagnostics("75%-0", &[("SyntaxError: \"%\" before \"-\" is ambiguous, so add a space after \"-\" if it is an operator or before it if it is a sign", 2, 4)]);
+        expect_diagnostics("7 %-3", &[("SyntaxError: \"%\" before \"-\" is ambiguous, so add a space after \"-\" if it is an operator or before it if it is a sign", 2, 4)]);
+        expect_value(Value::Integer(1), "7 % (3)");
+        expect_value(Value::Integer(1), "100% % 3");
+        expect_eval_error("true%");
+        expect_syntax_error("%");
+        expect_value(ratio(1, 2000), "5%%");
+    }
+
+    #[test]
+    fn parse_implicit_multiplication() {
+        let mut env = Environment::new();
+        expect_value_in(&mut env, Value::Integer(3), "x = 3");
+        expect_definition_in(&mut env, "f(y) = y + 1");
+        expect_value_in(&mut env, Value::Integer(6), "2x");
+        expect_value_in(&mut env, Value::Integer(6), "2 x");
+        expect_value_in(&mut env, Value::Integer(15), "3(4 + 1)");
+        expect_value_in(&mut env, Value::Integer(15), "3 (4 + 1)");
+        expect_value_in(&mut env, Value::Integer(12), "(1 + 2)(3 + 1)");
+        expect_value_in(&mut env, Value::Integer(9), "x x");
+        expect_eval_error_in(&mut env, "x(x)");
+        expect_value_in(&mut env, Value::Integer(8), "2f(x)");
+        expect_value_in(&mut env, Value::Integer(5), "f(4)");
+        expect_value_in(&mut env, Value::Integer(18), "2x^2");
+        expect_value_in(&mut env, Value::Integer(-6), "-2x");
+        expect_value_in(&mut env, Value::Integer(7), "2x + 1");
+        expect_value_in(&mut env, Value::Integer(9), "6 / 2x");
+        expect_value_in(&mut env, Value::Integer(12), "2x!");
+        expect_value_in(&mut env, Value::Integer(3), "(x)");
+        expect_value_in(&mut env, Value::Float(2.0 * std::f64::consts::PI), "2 pi");
+        expect_value_in(&mut env, Value::Integer(3), "7 % 2x");
+        expect_value_in(&mut env, Value::Integer(6), "x = 2x");
+        expect_value_in(&mut env, Value::Integer(6), "x");
+        expect_syntax_error("2 3");
+        expect_syntax_error("(2)3");
+        expect_syntax_error("2 if true then 1 else 0");
+        expect_syntax_error(")(");
+    }
+
+    #[test]
+    fn parse_strict() {
+        let strict = ParseOptions { implicit_multiplication: false };
+        for input in ["2x", "2 pi", "3(4 + 1)", "(1)(2)", "x y"] {
+            match parse_with(input, strict) {
+                Err(Error::SyntaxError(..)) => (),
+                bad_output => fail_test(input, "SyntaxError", bad_output),
+            }
+        }
+        match parse_with("sqrt(4)", strict).map(|statement| statement.execute(&mut Environment::new())) {
+            Ok(Ok(Some(Value::Float(2.0)))) => (),
+            bad_output => fail_test("sqrt(4)", Value::Float(2.0), bad_output),
+        }
+    }
+
+    fn expect_error_span_in(env: &mut Environment, input: &str, start: usize, end: usize) {
+        match parse(input).and_then(|statement| statement.execute(env)) {
+            Err(err) if err.span() == Some(Span::new(start, end)) => (),
+            bad_output => fail_test(input, Span::new(start, end), bad_output),
+        }
+    }
+
+    #[test]
+    fn error_spans() {
+        let mut env = Environment::new();
+        expect_error_span_in(&mut env, "1 + @ 2", 4, 5);
+        expect_error_span_in(&mut env, "1 + (2", 4, 5);
+        expect_error_span_in(&mut env, "1 + 2)", 5, 6);
+        expect_error_span_in(&mut env, "1 +", 3, 3);
+        expect_error_span_in(&mut env, "1++2", 1, 3);
+        expect_error_span_in(&mut env, "2 * ()", 4, 6);
+        expect_error_span_in(&mut env, "0x1G + 1", 0, 4);
+        expect_error_span_in(&mut env, "if true 1 else 2", 8, 9);
+        expect_error_span_in(&mut env, "g(x, x) = x", 1, 7);
+        expect_error_span_in(&mut env, "1 + 1/0", 4, 7);
+        expect_error_span_in(&mut env, "(1 + 1/0) * 2", 5, 8);
+        expect_error_span_in(&mut env, "true + (1)", 0, 10);
+        expect_error_span_in(&mut env, "2 * nope", 4, 8);
+        expect_error_span_in(&mut env, "1 + sqrt(-1)", 4, 12);
+        expect_definition_in(&mut env, "f(x) = 1 / x");
+        expect_error_span_in(&mut env, "2 * f(1 / 0)", 6, 11);
+        expect_error_span_in(&mut env, "2 * f(0)", 4, 8);
+    }
+
+    #[test]
+    fn render_errors() {
+        let render = |input| parse(input).and_then(|statement| statement.execute(&mut Environment::new())).unwrap_err().render(input);
+        assert_eq!(render("1 + (2"), "SyntaxError: unmatched \"(\"\n  1 + (2\n      ^");
+        assert_eq!(render("1 +"), "SyntaxError: unexpected end of input\n  1 +\n     ^");
+        assert_eq!(render("10 / (5 - 5)"), "EvalError: division by zero\n  10 / (5 - 5)\n  ^^^^^^^^^^^^");
+        assert_eq!(render("1 + @`"), "TokenizeError: unknown operator \"@`\"\n  1 + @`\n      ^^");
+    }
+
+    #[test]
+    fn parse_statements() {
+        let mut env = Environment::new();
+        expect_value_in(&mut env, Value::Integer(7), "a = 2; b = a * 3; b + 1");
+        expect_value_in(&mut env, Value::Integer(6), "b");
+        expect_value_in(&mut env, Value::Integer(2), "1; 2");
+        expect_value_in(&mut env, Value::Integer(1), "1;");
+        expect_value_in(&mut env, Value::Integer(1), " ; ;1 ;; ");
+        expect_value_in(&mut env, Value::Integer(9), "f(x) = x * x; f(3)");
+        expect_definition_in(&mut env, "d = 4; g(y) = y + d");
+        expect_value_in(&mut env, Value::Integer(5), "g(1)");
+        expect_value_in(&mut env, Value::Integer(1), "z = 1");
+        expect_eval_error_in(&mut env, "z = 2; 1 / 0; z = 3");
+        expect_value_in(&mut env, Value::Integer(2), "z");
+        expect_value_in(&mut env, Value::Integer(3), "if true then 1 else 2; 3");
+        expect_syntax_error("1; 2 +");
+        expect_syntax_error("1 +; 2");
+        expect_syntax_error("(1; 2)");
+        expect_syntax_error("f(1; 2)");
+    }
+
+    #[test]
+    fn parse_comments() {
+        let mut env = Environment::new();
+        expect_value_in(&mut env, Value::Integer(3), "1 + 2 # three");
+        expect_value_in(&mut env, Value::Integer(3), "1 + 2# three; 4");
+        expect_value_in(&mut env, Value::Integer(5), "x = 5 # five");
+        expect_definition_in(&mut env, "# nothing but a comment");
+        expect_definition_in(&mut env, "");
+        expect_value_in(&mut env, Value::Integer(6), "1 +\n# comment\n5");
+        expect_syntax_error("1 + # two");
+    }
+
+    #[test]
+    fn parse_unicode() {
+        let mut env = Environment::new();
+        expect_value_in(&mut env, Value::Integer(6), "2 × 3");
+        expect_value_in(&mut env, ratio(3, 2), "6 ÷ 4");
+        expect_value_in(&mut env, Value::Integer(3), "5 − 2");
+        expect_value_in(&mut env, Value::Integer(-3), "−3");
+        expect_value_in(&mut env, Value::Float(4.0), "√16");
+        expect_value_in(&mut env, Value::Float(3.0), "√(4 + 5)");
+        expect_value_in(&mut env, Value::Float(7.0), "√4 + 5");
+        expect_value_in(&mut env, Value::Float(2.0 * 2f64.sqrt()), "2√2");
+        expect_value_in(&mut env, Value::Float(2f64.sqrt() * 2f64.sqrt()), "√2√2");
+        expect_value_in(&mut env, Value::Float(6.0), "√4 √9");
+        expect_value_in(&mut env, Value::Float(6.0), "3√(2 + 2)");
+        expect_value_in(&mut env, Value::Float(2.0 * std::f64::consts::PI), "2π");
+        expect_value_in(&mut env, Value::Integer(3), "r = 3");
+        expect_value_in(&mut env, Value::Float(2.0 * std::f64::consts::PI * 3.0), "2πr");
+        expect_value_in(&mut env, Value::Integer(9), "r²");
+        expect_value_in(&mut env, Value::Integer(27), "r³");
+        expect_value_in(&mut env, Value::Integer(-9), "-r²");
+        expect_value_in(&mut env, Value::Integer(1 << 23), "2²³");
+        expect_value_in(&mut env, Value::Integer(512), "2^3²");
+        expect_value_in(&mut env, Value::Float(0.5), "2.0⁻¹");
+        expect_value_in(&mut env, Value::Integer(13), "2² + 3²");
+        expect_syntax_error("r²3");
+        expect_syntax_error("√");
+        expect_syntax_error("2⁻");
+        expect_error_span_in(&mut env, "1 + √", 7, 7);
+        expect_error_span_in(&mut env, "√-1", 0, 5);
+    }
+
+    fn expect_diagnostics(input: &str, expected: &[(&str, usize, usize)]) {
+        let (_, diagnostics) = parse_recovering(input, ParseOptions::default());
+        let actual: Vec<_> = diagnostics.iter().map(|diagnostic| (diagnostic.message.as_str(), diagnostic.span.start, diagnostic.span.end)).collect();
+        if actual != expected {
+            fail_test(input, expected, actual);
+        }
+    }
+
+    #[test]
+    fn recover_from_errors() {
+        expect_diagnostics("1 + 2", &[]);
+        expect_diagnostics("(1 + ) * (2 3", &[("SyntaxError: expected an operand before \")\"", 5, 6), ("SyntaxError: unmatched \"(\"", 9, 10), ("SyntaxError: invalid syntax \"3\"", 12, 13)]);
+        expect_diagnostics("1 + * 2 )", &[("SyntaxError: invalid syntax \"*\"", 4, 5), ("SyntaxError: unmatched \")\"", 8, 9)]);
+        expect_diagnostics("f(1,, 2) + @ + g(", &[("SyntaxError: expected an operand before \",\"", 4, 5), ("TokenizeError: unknown operator \"@\"", 11, 12), ("SyntaxError: unmatched \"(\"", 16, 17), ("SyntaxError: unexpected end of input", 17, 17)]);
+        expect_diagnostics("if 1 2 else", &[("SyntaxError: expected \"then\" but found \"2\"", 5, 6), ("SyntaxError: unexpected end of input", 11, 11)]);
+        expect_diagnostics("1 2 3", &[("SyntaxError: invalid syntax \"2\"", 2, 3), ("SyntaxError: invalid syntax \"3\"", 4, 5)]);
+        expect_diagnostics("x = (1 $", &[("SyntaxError: unmatched \"(\"", 4, 5), ("SyntaxError: \"$\" must be followed by an input number", 7, 8)]);
+        expect_diagnostics("1 +; 2 3; (4", &[("SyntaxError: expected an operand before \";\"", 3, 4), ("SyntaxError: invalid syntax \"3\"", 7, 8), ("SyntaxError: unmatched \"(\"", 10, 11)]);
+        expect_diagnostics("f(1 2; 3)", &[("SyntaxError: unmatched \"(\"", 1, 2), ("SyntaxError: invalid syntax \"2\"", 4, 5), ("SyntaxError: unmatched \")\"", 8, 9)]);
+    }
+
+    #[test]
+    fn partial_results() {
+        let mut env = Environment::new();
+        let (statement, diagnostics) = parse_recovering("1 + * 2 )", ParseOptions::default());
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(statement.execute(&mut env).ok(), Some(Some(Value::Integer(3))));
+        let (statement, _) = parse_recovering("(1 + ) * 2", ParseOptions::default());
+        assert!(matches!(statement, Statement::Expression(Expression { kind: ExpressionKind::Product(..), .. })));
+        assert!(matches!(statement.execute(&mut env), Err(Error::EvalError(_, Some(Span { start: 5, end: 6 })))));
+        let (statement, _) = parse_recovering("x = 2 +", ParseOptions::default());
+        assert!(matches!(statement, Statement::Assignment(name, _) if name == "x"));
+    }
 }
@@ -0,0 +1,143 @@
+use std::cmp::Ordering;
+
+use bigdecimal::BigDecimal;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{FromPrimitive, Signed};
+
+use super::{decimal, Error, Value};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Arity {
+    Exactly(usize),
+    AtLeast(usize),
+}
+
+#[derive(Debug)]
+pub struct Builtin {
+    pub name: &'static str,
+    pub arity: Arity,
+    func: fn(&[Value]) -> Value,
+    decimal: Option<DecimalFn>,
+}
+
+type DecimalFn = fn(&[BigDecimal], u64) -> Option<BigDecimal>;
+
+const PERIODIC: &[&str] = &["sin", "cos", "tan"];
+
+#[rustfmt::skip]
+const BUILTINS: &[Builtin] = &[
+    Builtin { name: "sqrt", arity: Arity::Exactly(1), func: |args| float_fn(args, f64::sqrt), decimal: Some(|args, precision| decimal::sqrt(&args[0], precision)) },
+    Builtin { name: "abs", arity: Arity::Exactly(1), func: abs, decimal: None },
+    Builtin { name: "exp", arity: Arity::Exactly(1), func: |args| float_fn(args, f64::exp), decimal: Some(|args, precision| decimal::exp(&args[0], precision)) },
+    Builtin { name: "ln", arity: Arity::Exactly(1), func: |args| float_fn(args, f64::ln), decimal: Some(|args, precision| decimal::ln(&args[0], precision)) },
+    Builtin { name: "log10", arity: Arity::Exactly(1), func: |args| float_fn(args, f64::log10), decimal: Some(|args, precision| decimal::log(&args[0], 10, precision)) },
+    Builtin { name: "log2", arity: Arity::Exactly(1), func: |args| float_fn(args, f64::log2), decimal: Some(|args, precision| decimal::log(&args[0], 2, precision)) },
+    Builtin { name: "sin", arity: Arity::Exactly(1), func: |args| float_fn(args, f64::sin), decimal: Some(|args, precision| decimal::sin(&args[0], precision)) },
+    Builtin { name: "cos", arity: Arity::Exactly(1), func: |args| float_fn(args, f64::cos), decimal: Some(|args, precision| decimal::cos(&args[0], precision)) },
+    Builtin { name: "tan", arity: Arity::Exactly(1), func: |args| float_fn(args, f64::tan), decimal: Some(|args, precision| decimal::tan(&args[0], precision)) },
+    Builtin { name: "asin", arity: Arity::Exactly(1), func: |args| float_fn(args, f64::asin), decimal: Some(|args, precision| decimal::asin(&args[0], precision)) },
+    Builtin { name: "acos", arity: Arity::Exactly(1), func: |args| float_fn(args, f64::acos), decimal: Some(|args, precision| decimal::acos(&args[0], precision)) },
+    Builtin { name: "atan", arity: Arity::Exactly(1), func: |args| float_fn(args, f64::atan), decimal: Some(|args, precision| decimal::atan(&args[0], precision)) },
+    Builtin { name: "atan2", arity: Arity::Exactly(2), func: |args| float_fn2(args, f64::atan2), decimal: Some(|args, precision| decimal::atan2(&args[0], &args[1], precision)) },
+    Builtin { name: "floor", arity: Arity::Exactly(1), func: |args| round_fn(args, f64::floor, BigRational::floor), decimal: None },
+    Builtin { name: "ceil", arity: Arity::Exactly(1), func: |args| round_fn(args, f64::ceil, BigRational::ceil), decimal: None },
+    Builtin { name: "round", arity: Arity::Exactly(1), func: |args| round_fn(args, f64::round, BigRational::round), decimal: None },
+    Builtin { name: "min", arity: Arity::AtLeast(1), func: |args| extremum(args, Ordering::Less), decimal: None },
+    Builtin { name: "max", arity: Arity::AtLeast(1), func: |args| extremum(args, Ordering::Greater), decimal: None },
+    Builtin { name: "hypot", arity: Arity::Exactly(2), func: |args| float_fn2(args, f64::hypot), decimal: Some(|args, precision| decimal::hypot(&args[0], &args[1], precision)) },
+    Builtin { name: "decimal", arity: Arity::Exactly(1), func: |args| Value::Float(float(&args[0])), decimal: Some(|args, _| Some(args[0].clone())) },
+];
+
+pub fn find(name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|builtin| builtin.name == name)
+}
+
+impl Builtin {
+    /// Computes in decimals if `decimal_precision` is set and every argument is exact.
+    pub fn call(&self, args: &[Value], decimal_precision: Option<u64>) -> Result<Value, Error> {
+        let n_args_ok = match self.arity {
+            Arity::Exactly(n) => args.len() == n,
+            Arity::AtLeast(n) => args.len() >= n,
+        };
+        if !n_args_ok {
+            let expected = match self.arity {
+                Arity::Exactly(n) => format!("{n}"),
+                Arity::AtLeast(n) => format!("at least {n}"),
+            };
+            return Err(Error::EvalError(format!("{} takes {expected} argument(s) but {} were given", self.name, args.len()), None));
+        }
+        if let Some(arg) = args.iter().find(|arg| arg.as_float().is_none()) {
+            return Err(Error::EvalError(format!("type error: {} expects numbers, not {}", self.name, arg.type_name()), None));
+        }
+        if let (Some(precision), Some(decimal_fn)) = (decimal_precision, self.decimal) {
+            if let Some(decimal_args) = args.iter().map(|arg| arg.as_decimal(precision)).collect::<Option<Vec<_>>>() {
+                let max_exponent = decimal::max_trig_exponent(precision);
+                if PERIODIC.contains(&self.name) && decimal_args.iter().any(|arg| decimal::exponent(arg) > max_exponent) {
+                    return Err(Error::EvalError(format!("an argument to {} is out of range, with an exponent beyond {max_exponent} at this precision", self.name), None));
+                }
+                return match decimal_fn(&decimal_args, precision) {
+                    Some(num) => Value::from_decimal(&num, precision),
+                    None => Err(self.not_finite(args)),
+                };
+            }
+        }
+        // Functions with a decimal counterpart are the inexact ones, which otherwise compute in floats, so e.g. `sqrt(2^1100)` can't quietly become `inf`.
+        if self.decimal.is_some() && args.iter().any(|arg| !matches!(arg, Value::Float(_)) && !float(arg).is_finite()) {
+            return Err(Error::EvalError(format!("an argument to {} is too large to convert to a float", self.name), None));
+        }
+        match (self.func)(args) {
+            // only finite inputs are checked, so that e.g. infinities can still pass through `abs` or `max`
+            Value::Float(num) if !num.is_finite() && args.iter().all(|arg| float(arg).is_finite()) => Err(self.not_finite(args)),
+            val => Ok(val),
+        }
+    }
+
+    fn not_finite(&self, args: &[Value]) -> Error {
+        let args = args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>().join(", ");
+        Error::EvalError(format!("{}({args}) is not a finite real number", self.name), None)
+    }
+}
+
+fn float(arg: &Value) -> f64 {
+    arg.as_float().expect("builtin arguments should be checked to be numbers")
+}
+
+fn float_fn(args: &[Value], f: fn(f64) -> f64) -> Value {
+    Value::Float(f(float(&args[0])))
+}
+
+fn float_fn2(args: &[Value], f: fn(f64, f64) -> f64) -> Value {
+    Value::Float(f(float(&args[0]), float(&args[1])))
+}
+
+fn round_fn(args: &[Value], f: fn(f64) -> f64, exact: fn(&BigRational) -> BigRational) -> Value {
+    match args[0] {
+        Value::Rational(_) | Value::Decimal(..) => Value::from(exact(&args[0].as_rational().expect("rationals and decimals should be exact"))),
+        Value::Float(num) => match BigInt::from_f64(f(num)) {
+            Some(num) => Value::from(num),
+            None => Value::Float(f(num)),
+        },
+        ref val => val.clone(),
+    }
+}
+
+fn abs(args: &[Value]) -> Value {
+    match args[0] {
+        Value::Integer(num) => num.checked_abs().map_or_else(|| Value::from(BigInt::from(num).abs()), Value::Integer),
+        Value::BigInteger(ref num) => Value::from(num.abs()),
+        Value::Rational(ref num) => Value::Rational(num.abs()),
+        Value::Decimal(ref num, precision) => Value::Decimal(num.abs(), precision),
+        ref val => Value::Float(float(val).abs()),
+    }
+}
+
+fn extremum(args: &[Value], better: Ordering) -> Value {
+    let mut best = &args[0];
+    for arg in &args[1..] {
+        if arg.numeric_cmp(best) == Some(better) {
+            best = arg;
+        }
+    }
+    best.clone()
+}
@@ -0,0 +1,41 @@
+use std::f64::consts;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Namespace {
+    Math,
+    Physics,
+}
+
+#[derive(Debug)]
+pub struct Constant {
+    pub name: &'static str,
+    pub namespace: Namespace,
+    pub value: f64,
+    pub description: &'static str,
+}
+
+/// Physical constants are CODATA 2018 values in SI units.
+#[rustfmt::skip]
+pub const CONSTANTS: &[Constant] = &[
+    Constant { name: "pi", namespace: Namespace::Math, value: consts::PI, description: "ratio of a circle's circumference to its diameter" },
+    Constant { name: "e", namespace: Namespace::Math, value: consts::E, description: "base of the natural logarithm" },
+    Constant { name: "tau", namespace: Namespace::Math, value: consts::TAU, description: "ratio of a circle's circumference to its radius" },
+    Constant { name: "phi", namespace: Namespace::Math, value: 1.618033988749895, description: "golden ratio" },
+    Constant { name: "inf", namespace: Namespace::Math, value: f64::INFINITY, description: "infinity" },
+    Constant { name: "c", namespace: Namespace::Physics, value: 299792458.0, description: "speed of light in vacuum (m/s)" },
+    Constant { name: "h", namespace: Namespace::Physics, value: 6.62607015e-34, description: "Planck constant (J s)" },
+    Constant { name: "hbar", namespace: Namespace::Physics, value: 1.054571817e-34, description: "reduced Planck constant (J s)" },
+    Constant { name: "G", namespace: Namespace::Physics, value: 6.6743e-11, description: "Newtonian constant of gravitation (m^3 / kg s^2)" },
+    Constant { name: "g_0", namespace: Namespace::Physics, value: 9.80665, description: "standard acceleration of gravity (m / s^2)" },
+    Constant { name: "N_A", namespace: Namespace::Physics, value: 6.02214076e23, description: "Avogadro constant (1 / mol)" },
+    Constant { name: "k_B", namespace: Namespace::Physics, value: 1.380649e-23, description: "Boltzmann constant (J / K)" },
+    Constant { name: "R", namespace: Namespace::Physics, value: 8.314462618, description: "molar gas constant (J / mol K)" },
+    Constant { name: "q_e", namespace: Namespace::Physics, value: 1.602176634e-19, description: "elementary charge (C)" },
+    Constant { name: "m_e", namespace: Namespace::Physics, value: 9.1093837015e-31, description: "electron mass (kg)" },
+    Constant { name: "m_p", namespace: Namespace::Physics, value: 1.67262192369e-27, description: "proton mass (kg)" },
+    Constant { name: "eps_0", namespace: Namespace::Physics, value: 8.8541878128e-12, description: "vacuum electric permittivity (F / m)" },
+];
+
+pub fn find(name: &str) -> Option<&'static Constant> {
+    CONSTANTS.iter().find(|constant| constant.name == name)
+}
@@ -0,0 +1,425 @@
+use std::cmp::Ordering;
+use std::num::NonZeroU64;
+
+use bigdecimal::{BigDecimal, Context, RoundingMode};
+use num_bigint::{BigInt, Sign};
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+const GUARD_DIGITS: u64 = 10;
+
+pub const MAX_EXPONENT: i64 = 100_000;
+
+pub fn round(num: &BigDecimal, precision: u64) -> BigDecimal {
+    let precision = NonZeroU64::new(precision).expect("precision should be at least 1");
+    num.with_precision_round(precision, RoundingMode::HalfEven).normalized()
+}
+
+pub fn exponent(num: &BigDecimal) -> i64 {
+    if num.is_zero() {
+        return 0;
+    }
+    let (digits, scale) = num.as_bigint_and_exponent();
+    digit_count(&digits) as i64 - 1 - scale
+}
+
+fn digit_count(num: &BigInt) -> u64 {
+    num.magnitude().to_string().len() as u64
+}
+
+fn ten_to_the(power: u64) -> BigInt {
+    BigInt::from(10).pow(u32::try_from(power).expect("powers of ten should be bounded by MAX_EXPONENT"))
+}
+
+pub fn to_rational(num: &BigDecimal) -> BigRational {
+    let (digits, scale) = num.as_bigint_and_exponent();
+    match scale {
+        0.. => BigRational::new(digits, ten_to_the(scale.unsigned_abs())),
+        _ => BigRational::from_integer(digits * ten_to_the(scale.unsigned_abs())),
+    }
+}
+
+pub fn from_rational(num: &BigRational, precision: u64) -> BigDecimal {
+    if num.is_zero() {
+        return BigDecimal::zero();
+    }
+    // Scale the numerator so that the quotient has at least one more digit than is needed.
+    let scale = precision as i64 + 1 - (digit_count(num.numer()) as i64 - digit_count(num.denom()) as i64);
+    let (numer, denom) = match scale {
+        0.. => (num.numer() * ten_to_the(scale.unsigned_abs()), num.denom().clone()),
+        _ => (num.numer().clone(), num.denom() * ten_to_the(scale.unsigned_abs())),
+    };
+    let (quotient, remainder) = numer.div_rem(&denom);
+    // A nonzero remainder becomes one more digit, so that rounding can tell e.g. 0.50001 from 0.5.
+    let (quotient, scale) = match remainder.sign() {
+        Sign::NoSign => (quotient, scale),
+        sign => (quotient * 10 + BigInt::from_biguint(sign, One::one()), scale + 1),
+    };
+    round(&BigDecimal::new(quotient, scale), precision)
+}
+
+pub fn divide(lhs: &BigDecimal, rhs: &BigDecimal, precision: u64) -> BigDecimal {
+    let (lhs_digits, lhs_scale) = lhs.as_bigint_and_exponent();
+    let (rhs_digits, rhs_scale) = rhs.as_bigint_and_exponent();
+    // Dividing the digits gives the significant digits of the quotient, which the scales then shift into place.
+    let (digits, scale) = from_rational(&BigRational::new(lhs_digits, rhs_digits), precision).into_bigint_and_exponent();
+    BigDecimal::new(digits, scale + lhs_scale - rhs_scale)
+}
+
+pub fn format(num: &BigDecimal) -> String {
+    let num = num.normalized();
+    let exponent = exponent(&num);
+    if (-5..17).contains(&exponent) {
+        return num.to_plain_string();
+    }
+    let (digits, _) = num.as_bigint_and_exponent();
+    let sign = if digits.is_negative() { "-" } else { "" };
+    let digits = digits.magnitude().to_string();
+    let (leading, rest) = digits.split_at(1);
+    let mantissa = if rest.is_empty() { String::from(leading) } else { format!("{leading}.{rest}") };
+    format!("{sign}{mantissa}e{exponent}")
+}
+
+fn mul(lhs: &BigDecimal, rhs: &BigDecimal, scale: i64) -> BigDecimal {
+    (lhs * rhs).with_scale_round(scale, RoundingMode::HalfEven)
+}
+
+fn div(lhs: &BigDecimal, rhs: &BigDecimal, scale: i64) -> BigDecimal {
+    let (lhs_digits, lhs_scale) = lhs.as_bigint_and_exponent();
+    let (rhs_digits, rhs_scale) = rhs.as_bigint_and_exponent();
+    let shift = scale + rhs_scale - lhs_scale;
+    let quotient = match shift {
+        0.. => lhs_digits * ten_to_the(shift.unsigned_abs()) / rhs_digits,
+        _ => lhs_digits / (rhs_digits * ten_to_the(shift.unsigned_abs())),
+    };
+    BigDecimal::new(quotient, scale)
+}
+
+fn sqrt_to_scale(num: &BigDecimal, scale: i64) -> BigDecimal {
+    let digits = (scale + exponent(num) / 2 + 2).max(1) as u64;
+    let context = Context::new(NonZeroU64::new(digits).expect("digits should be at least 1"), RoundingMode::HalfEven);
+    num.sqrt_with_context(&context).expect("square roots should only be taken of non-negative numbers").with_scale_round(scale, RoundingMode::HalfEven)
+}
+
+fn arctangent_series(x: &BigDecimal, scale: i64, alternating: bool) -> BigDecimal {
+    let x_squared = mul(x, x, scale);
+    let mut power = x.clone();
+    let mut sum = x.clone();
+    for n in (3..).step_by(2) {
+        power = mul(&power, &x_squared, scale);
+        let term = div(&power, &BigDecimal::from(n), scale);
+        if term.is_zero() {
+            break;
+        }
+        if alternating && n % 4 == 3 {
+            sum -= term;
+        } else {
+            sum += term;
+        }
+    }
+    sum
+}
+
+fn sine_series(x: &BigDecimal, scale: i64, start: u64) -> BigDecimal {
+    let x_squared = mul(x, x, scale);
+    let mut term = if start == 1 { x.clone() } else { BigDecimal::one() };
+    let mut sum = term.clone();
+    for n in (start + 2..).step_by(2) {
+        term = -div(&mul(&term, &x_squared, scale), &BigDecimal::from((n - 1) * n), scale);
+        if term.is_zero() {
+            break;
+        }
+        sum += &term;
+    }
+    sum
+}
+
+fn pi_to_scale(scale: i64) -> BigDecimal {
+    // Machin's formula: pi = 16 atan(1/5) - 4 atan(1/239)
+    let atan_inverse = |n: i64| arctangent_series(&div(&BigDecimal::one(), &BigDecimal::from(n), scale), scale, true);
+    atan_inverse(5) * BigDecimal::from(16) - atan_inverse(239) * BigDecimal::from(4)
+}
+
+fn ln2_to_scale(scale: i64) -> BigDecimal {
+    // ln 2 = 2 atanh(1/3)
+    arctangent_series(&div(&BigDecimal::one(), &BigDecimal::from(3), scale), scale, false).double()
+}
+
+fn ln10_to_scale(scale: i64) -> BigDecimal {
+    // ln 10 = 3 ln 2 + ln 1.25, and ln 1.25 = 2 atanh(1/9)
+    ln2_to_scale(scale) * BigDecimal::from(3) + arctangent_series(&div(&BigDecimal::one(), &BigDecimal::from(9), scale), scale, false).double()
+}
+
+fn working_scale(precision: u64, exponent: i64) -> i64 {
+    (precision + GUARD_DIGITS) as i64 - exponent.min(0)
+}
+
+pub fn pi(precision: u64) -> BigDecimal {
+    round(&pi_to_scale(working_scale(precision, 0)), precision)
+}
+
+pub fn sqrt(x: &BigDecimal, precision: u64) -> Option<BigDecimal> {
+    if x.is_negative() {
+        return None;
+    }
+    Some(round(&sqrt_to_scale(x, working_scale(precision, exponent(x) / 2)), precision))
+}
+
+pub fn hypot(x: &BigDecimal, y: &BigDecimal, precision: u64) -> Option<BigDecimal> {
+    sqrt(&(x * x + y * y), precision)
+}
+
+pub fn exp(x: &BigDecimal, precision: u64) -> Option<BigDecimal> {
+    // exp(x) = 10^k exp(r), where r = x - k ln 10 is small
+    let k = (x.to_f64()? / std::f64::consts::LN_10).round();
+    // A result this large or small is out of range, so only its power of ten is returned, for the caller to reject.
+    if k.abs() > MAX_EXPONENT as f64 {
+        let k = if k > 0.0 { MAX_EXPONENT + 1 } else { -MAX_EXPONENT - 1 };
+        return Some(BigDecimal::new(BigInt::one(), -k));
+    }
+    let k = k as i64;
+    let scale = working_scale(precision, 0) + exponent(&BigDecimal::from(k)) + 1;
+    let r = x - ln10_to_scale(scale) * BigDecimal::from(k);
+    let exp_r = exp_series(&r.with_scale_round(scale, RoundingMode::HalfEven), scale);
+    let (digits, exp_scale) = exp_r.as_bigint_and_exponent();
+    Some(round(&BigDecimal::new(digits, exp_scale - k), precision))
+}
+
+fn exp_series(x: &BigDecimal, scale: i64) -> BigDecimal {
+    let mut term = BigDecimal::one();
+    let mut sum = BigDecimal::one();
+    for n in 1.. {
+        term = div(&mul(&term, x, scale), &BigDecimal::from(n), scale);
+        if term.is_zero() {
+            break;
+        }
+        sum += &term;
+    }
+    sum
+}
+
+pub fn ln(x: &BigDecimal, precision: u64) -> Option<BigDecimal> {
+    ln_to_scale(x, precision).map(|(ln, _)| round(&ln, precision))
+}
+
+fn ln_to_scale(x: &BigDecimal, precision: u64) -> Option<(BigDecimal, i64)> {
+    if !x.is_positive() {
+        return None;
+    }
+    // ln(x) = k ln 10 + j ln 2 + ln(m), where x = 10^k 2^j m and m is near 1
+    let k = exponent(x);
+    let (digits, scale) = x.as_bigint_and_exponent();
+    let mut m = BigDecimal::new(digits, scale + k);
+    let mut j = 0;
+    let limit = BigDecimal::new(BigInt::from(125), 2);
+    while m > limit {
+        m = m.half();
+        j += 1;
+    }
+    let near_one = &m - BigDecimal::one();
+    let scale = working_scale(precision, exponent(&near_one)) + exponent(&BigDecimal::from(k)) + 1;
+    let y = div(&near_one, &(&m + BigDecimal::one()), scale);
+    let ln_m = arctangent_series(&y, scale, false).double();
+    Some((ln_m + ln2_to_scale(scale) * BigDecimal::from(j) + ln10_to_scale(scale) * BigDecimal::from(k), scale))
+}
+
+pub fn log(x: &BigDecimal, base: u32, precision: u64) -> Option<BigDecimal> {
+    let (ln, scale) = ln_to_scale(x, precision)?;
+    let ln_base = if base == 2 { ln2_to_scale(scale) } else { ln10_to_scale(scale) };
+    Some(round(&div(&ln, &ln_base, scale), precision))
+}
+
+pub fn max_trig_exponent(precision: u64) -> i64 {
+    precision as i64 + 1000
+}
+
+fn rounds_to_itself(x: &BigDecimal, precision: u64) -> bool {
+    2 * exponent(x) < -((precision + GUARD_DIGITS) as i64)
+}
+
+fn sine(x: &BigDecimal, precision: u64, shift: i64) -> Option<BigDecimal> {
+    let magnitude = exponent(x).max(0);
+    if magnitude > max_trig_exponent(precision) {
+        return None;
+    }
+    // sin(x) = x - x^3/6 + ..., which rounds to x when x is tiny
+    if rounds_to_itself(x, precision) {
+        return Some(if shift == 0 { round(x, precision) } else { BigDecimal::one() });
+    }
+    // Reduce to r = x - n pi/2 with |r| < pi/2, then use the identities for each quadrant.
+    let scale = working_scale(precision, exponent(x)) + magnitude;
+    let half_pi = pi_to_scale(scale).half();
+    let n = div(x, &half_pi, 0);
+    let r = x - mul(&n, &half_pi, scale);
+    let scale = working_scale(precision, exponent(&r));
+    let r = r.with_scale_round(scale, RoundingMode::HalfEven);
+    let (n, _) = n.into_bigint_and_exponent();
+    let result = match (n + shift).mod_floor(&BigInt::from(4)).to_u8() {
+        Some(0) => sine_series(&r, scale, 1),
+        Some(1) => sine_series(&r, scale, 0),
+        Some(2) => -sine_series(&r, scale, 1),
+        _ => -sine_series(&r, scale, 0),
+    };
+    Some(round(&result, precision))
+}
+
+pub fn sin(x: &BigDecimal, precision: u64) -> Option<BigDecimal> {
+    sine(x, precision, 0)
+}
+
+pub fn cos(x: &BigDecimal, precision: u64) -> Option<BigDecimal> {
+    sine(x, precision, 1)
+}
+
+pub fn tan(x: &BigDecimal, precision: u64) -> Option<BigDecimal> {
+    let working = precision + GUARD_DIGITS;
+    let (sin, cos) = (sine(x, working, 0)?, sine(x, working, 1)?);
+    if cos.is_zero() {
+        return None;
+    }
+    Some(divide(&sin, &cos, precision))
+}
+
+fn atan_to_scale(x: &BigDecimal, scale: i64) -> BigDecimal {
+    if x.abs() > BigDecimal::one() {
+        // atan(x) = ±pi/2 - atan(1/x)
+        let half_pi = pi_to_scale(scale).half();
+        let atan_inverse = atan_to_scale(&div(&BigDecimal::one(), x, scale), scale);
+        return if x.is_positive() { half_pi - atan_inverse } else { -half_pi - atan_inverse };
+    }
+    // Halve the angle twice using atan(x) = 2 atan(x / (1 + sqrt(1 + x^2))), so that the series converges quickly.
+    let mut x = x.clone();
+    for _ in 0..2 {
+        let denominator = BigDecimal::one() + sqrt_to_scale(&(BigDecimal::one() + mul(&x, &x, scale)), scale);
+        x = div(&x, &denominator, scale);
+    }
+    arctangent_series(&x, scale, true) * BigDecimal::from(4)
+}
+
+pub fn atan(x: &BigDecimal, precision: u64) -> Option<BigDecimal> {
+    if rounds_to_itself(x, precision) {
+        return Some(round(x, precision));
+    }
+    Some(round(&atan_to_scale(x, working_scale(precision, exponent(x))), precision))
+}
+
+pub fn asin(x: &BigDecimal, precision: u64) -> Option<BigDecimal> {
+    if rounds_to_itself(x, precision) {
+        return Some(round(x, precision));
+    }
+    let scale = working_scale(precision, exponent(x));
+    match x.abs().cmp(&BigDecimal::one()) {
+        Ordering::Greater => None,
+        // asin(±1) = ±pi/2
+        Ordering::Equal => {
+            let half_pi = pi_to_scale(scale).half();
+            Some(round(&if x.is_negative() { -half_pi } else { half_pi }, precision))
+        },
+        // asin(x) = atan(x / sqrt(1 - x^2))
+        Ordering::Less => Some(round(&atan_to_scale(&div(x, &sqrt_to_scale(&(BigDecimal::one() - x * x), scale), scale), scale), precision)),
+    }
+}
+
+pub fn acos(x: &BigDecimal, precision: u64) -> Option<BigDecimal> {
+    // acos(x) = pi/2 - asin(x)
+    let asin = asin(x, precision + GUARD_DIGITS)?;
+    Some(round(&(pi_to_scale(working_scale(precision, 0)).half() - asin), precision))
+}
+
+pub fn atan2(y: &BigDecimal, x: &BigDecimal, precision: u64) -> Option<BigDecimal> {
+    let working = precision + GUARD_DIGITS;
+    let scale = working_scale(working, 0);
+    let angle = match (x.sign(), y.sign()) {
+        (Sign::NoSign, Sign::NoSign) => BigDecimal::zero(),
+        (Sign::NoSign, Sign::Minus) => -pi_to_scale(scale).half(),
+        (Sign::NoSign, _) => pi_to_scale(scale).half(),
+        (x_sign, y_sign) => {
+            let atan = atan(&divide(y, x, working), working)?;
+            match (x_sign, y_sign) {
+                (Sign::Plus, _) => atan,
+                (_, Sign::Minus) => atan - pi_to_scale(scale),
+                _ => atan + pi_to_scale(scale),
+            }
+        },
+    };
+    Some(round(&angle, precision))
+}
+
+pub fn pow(x: &BigDecimal, y: &BigDecimal, precision: u64) -> Option<BigDecimal> {
+    // The error in `y ln x` becomes the relative error of the result, and `y ln x` has up to 6 digits before the point.
+    let working = precision + GUARD_DIGITS + 6;
+    let ln = ln(x, working)?;
+    exp(&(ln * y), precision)
+}
+
+pub fn constant(name: &str, value: f64, precision: u64) -> Option<BigDecimal> {
+    let working = precision + GUARD_DIGITS;
+    match name {
+        "pi" => Some(pi(precision)),
+        "tau" => Some(round(&pi(working).double(), precision)),
+        "e" => exp(&BigDecimal::one(), precision),
+        "phi" => Some(round(&(BigDecimal::one() + sqrt(&BigDecimal::from(5), working)?).half(), precision)),
+        // The shortest representation of a float gives back the digits it was written with, e.g. 6.62607015e-34.
+        _ => format!("{value:e}").parse::<BigDecimal>().ok().map(|num| round(&num, precision)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(num: &str) -> BigDecimal {
+        num.parse().unwrap()
+    }
+
+    fn digits(num: Option<BigDecimal>) -> String {
+        format(&num.expect("result should be a real number"))
+    }
+
+    #[test]
+    fn rounding() {
+        assert_eq!(format(&round(&dec("2.5"), 1)), "2");
+        assert_eq!(format(&round(&dec("3.5"), 1)), "4");
+        assert_eq!(format(&round(&dec("1.2500"), 5)), "1.25");
+        assert_eq!(format(&from_rational(&BigRational::new(BigInt::from(2), BigInt::from(3)), 5)), "0.66667");
+        // 2.500000001 is above halfway, so it doesn't round to even.
+        assert_eq!(format(&from_rational(&BigRational::new(BigInt::from(2_500_000_001_i64), BigInt::from(10).pow(9)), 1)), "3");
+        assert_eq!(format(&divide(&dec("1e-30"), &dec("3e30"), 3)), "3.33e-61");
+    }
+
+    #[test]
+    fn formatting() {
+        assert_eq!(format(&dec("12345678901234567")), "12345678901234567");
+        assert_eq!(format(&dec("1e17")), "1e17");
+        assert_eq!(format(&dec("0.00001")), "0.00001");
+        assert_eq!(format(&dec("-0.000001234")), "-1.234e-6");
+        assert_eq!(format(&dec("100.0")), "100");
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(format(&pi(100)), "3.141592653589793238462643383279502884197169399375105820974944592307816406286208998628034825342117068");
+        assert_eq!(digits(cos(&dec("1"), 20)), "0.5403023058681397174");
+        assert_eq!(digits(tan(&dec("1"), 20)), "1.5574077246549022305");
+        assert_eq!(digits(sin(&dec("100"), 20)), "-0.50636564110975879366");
+        assert_eq!(digits(sin(&dec("1e6"), 20)), "-0.34999350217129295212");
+        assert_eq!(digits(atan(&dec("1"), 20)), "0.78539816339744830962");
+        assert_eq!(digits(asin(&dec("0.5"), 20)), "0.52359877559829887308");
+        assert_eq!(digits(acos(&dec("0"), 20)), "1.5707963267948966192");
+        assert_eq!(digits(atan2(&dec("-1"), &dec("-1"), 20)), "-2.3561944901923449288");
+        assert_eq!(digits(log(&dec("10"), 2, 20)), "3.3219280948873623479");
+        assert_eq!(digits(ln(&dec("1e-30"), 20)), "-69.077552789821370521");
+        assert_eq!(digits(exp(&dec("-10"), 20)), "0.000045399929762484851536");
+        assert_eq!(digits(exp(&dec("230"), 20)), "7.7220184999838357176e99");
+        assert_eq!(digits(sqrt(&dec("1e-41"), 20)), "3.162277660168379332e-21");
+        assert_eq!(ln(&dec("-1"), 20), None);
+        assert_eq!(acos(&dec("1.01"), 20), None);
+        assert_eq!(sin(&dec("1e1021"), 20), None);
+        assert_eq!(digits(sin(&dec("1e1020"), 20)), "0.9123910514333347985");
+        assert_eq!(digits(tan(&dec("-2e-30"), 20)), "-2e-30");
+        assert_eq!(digits(atan2(&dec("1e-500"), &dec("-1e500"), 20)), "3.1415926535897932385");
+        assert_eq!(exponent(&exp(&dec("1e10"), 20).unwrap()), MAX_EXPONENT + 1);
+        assert_eq!(exponent(&exp(&dec("-1e10"), 20).unwrap()), -MAX_EXPONENT - 1);
+    }
+}
@@ -0,0 +1,21 @@
+use super::span::Span;
+use super::Error;
+
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub message: String,
+    pub span: Span,
+}
+
+impl Diagnostic {
+    pub fn render(&self, input: &str) -> String {
+        format!("{}\n{}", self.message, self.span.underline(input))
+    }
+}
+
+impl From<Error> for Diagnostic {
+    fn from(err: Error) -> Self {
+        let span = err.span().unwrap_or(Span::new(0, 0));
+        Self { message: err.to_string(), span }
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::constants::{self, Namespace};
+use super::{builtins, decimal, Error, Expression, Value};
+
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 100;
+
+/// Counts every nested expression across all active calls.
+pub const MAX_EVAL_DEPTH: usize = 10_000;
+
+/// Leaves room for `MAX_EVAL_DEPTH` even in debug builds.
+pub const STACK_SIZE: usize = 256 << 20;
+
+const ANS: &str = "ans";
+
+#[derive(Debug)]
+pub struct Function {
+    pub params: Vec<String>,
+    pub body: Expression,
+}
+
+#[derive(Debug)]
+pub struct Environment {
+    variables: HashMap<String, Value>,
+    functions: HashMap<String, Rc<Function>>,
+    call_stack: Vec<HashMap<String, Value>>,
+    max_call_depth: usize,
+    eval_depth: usize,
+    decimal_precision: Option<u64>,
+    results: HashMap<usize, Value>,
+    last_result: Option<Value>,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Environment { variables: HashMap::new(), functions: HashMap::new(), call_stack: Vec::new(), max_call_depth: DEFAULT_MAX_CALL_DEPTH, eval_depth: 0, decimal_precision: None, results: HashMap::new(), last_result: None }
+    }
+
+    pub fn set_max_call_depth(&mut self, max_call_depth: usize) {
+        self.max_call_depth = max_call_depth;
+    }
+
+    pub fn enter_eval(&mut self) -> Result<(), Error> {
+        if self.eval_depth >= MAX_EVAL_DEPTH {
+            return Err(Error::EvalError(format!("expression is nested more than {MAX_EVAL_DEPTH} levels deep, counting function calls"), None));
+        }
+        self.eval_depth += 1;
+        Ok(())
+    }
+
+    pub fn exit_eval(&mut self) {
+        self.eval_depth -= 1;
+    }
+
+    /// `None` turns decimal mode off.
+    pub fn set_decimal_precision(&mut self, precision: Option<u64>) {
+        self.decimal_precision = precision;
+    }
+
+    pub fn decimal_precision(&self) -> Option<u64> {
+        self.decimal_precision
+    }
+
+    /// Physical constants come last, so variables may shadow them.
+    pub fn get_variable(&self, name: &str) -> Result<Value, Error> {
+        if let Some(val) = self.call_stack.last().and_then(|args| args.get(name)) {
+            return Ok(val.clone());
+        }
+        if let Some(constant) = constants::find(name).filter(|constant| constant.namespace == Namespace::Math || !self.variables.contains_key(name)) {
+            // In decimal mode, constants are computed to the current precision.
+            return Ok(match self.decimal_precision.and_then(|precision| Some(Value::Decimal(decimal::constant(constant.name, constant.value, precision)?, precision))) {
+                Some(val) => val,
+                None => Value::Float(constant.value),
+            });
+        }
+        if name == ANS {
+            return self.last_result.clone().ok_or_else(|| Error::EvalError(format!("{ANS} has no result yet"), None));
+        }
+        self.variables.get(name).cloned().ok_or_else(|| Error::EvalError(format!("undefined variable {name}"), None))
+    }
+
+    pub fn set_variable(&mut self, name: String, val: Value) -> Result<(), Error> {
+        if constants::find(&name).is_some_and(|constant| constant.namespace == Namespace::Math) {
+            return Err(Error::EvalError(format!("cannot assign to constant {name}"), None));
+        }
+        if name == ANS {
+            return Err(Error::EvalError(format!("cannot assign to {ANS}"), None));
+        }
+        self.variables.insert(name, val);
+        Ok(())
+    }
+
+    pub fn get_result(&self, input_number: usize) -> Option<Value> {
+        self.results.get(&input_number).cloned()
+    }
+
+    /// Also becomes the value of `ans`.
+    pub fn record_result(&mut self, input_number: usize, val: Value) {
+        self.results.insert(input_number, val.clone());
+        self.last_result = Some(val);
+    }
+
+    pub fn get_function(&self, name: &str) -> Option<Rc<Function>> {
+        self.functions.get(name).cloned()
+    }
+
+    pub fn set_function(&mut self, name: String, func: Rc<Function>) {
+        self.functions.insert(name, func);
+    }
+
+    /// User-defined functions shadow builtins.
+    pub fn call(&mut self, name: &str, args: Vec<Value>) -> Result<Value, Error> {
+        let Some(func) = self.get_function(name) else {
+            return match builtins::find(name) {
+                Some(builtin) => builtin.call(&args, self.decimal_precision),
+                None => Err(Error::EvalError(format!("undefined function {name}"), None)),
+            };
+        };
+        if args.len() != func.params.len() {
+            return Err(Error::EvalError(format!("{name} takes {} argument(s) but {} were given", func.params.len(), args.len()), None));
+        }
+        if self.call_stack.len() >= self.max_call_depth {
+            return Err(Error::EvalError(format!("maximum call depth of {} exceeded", self.max_call_depth), None));
+        }
+        self.call_stack.push(func.params.iter().cloned().zip(args).collect());
+        let result = func.body.eval(self);
+        self.call_stack.pop();
+        result
+    }
+}
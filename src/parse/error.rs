@@ -1,22 +1,58 @@
+use super::span::Span;
+
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug)]
 pub enum Error {
-    TokenizeError(char),
-    SyntaxError(String),
-    EvalError(String),
+    TokenizeError(String, Span),
+    SyntaxError(String, Span),
+    /// The span is filled in by the innermost expression that fails.
+    EvalError(String, Option<Span>),
+}
+
+impl Error {
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Self::TokenizeError(_, span) | Self::SyntaxError(_, span) => Some(*span),
+            Self::EvalError(_, span) => *span,
+        }
+    }
+
+    /// Points an evaluation error at `span` unless it already has one.
+    pub fn or_at(self, span: Span) -> Self {
+        match self {
+            Self::EvalError(message, None) => Self::EvalError(message, Some(span)),
+            err => err,
+        }
+    }
+
+    /// Points an evaluation error at `span` even if it already has one.
+    pub fn at(self, span: Span) -> Self {
+        match self {
+            Self::EvalError(message, _) => Self::EvalError(message, Some(span)),
+            err => err,
+        }
+    }
+
+    pub fn render(&self, input: &str) -> String {
+        match self.span() {
+            Some(span) => format!("{self}\n{}", span.underline(input)),
+            None => self.to_string(),
+        }
+    }
 }
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(&match self {
-            Self::TokenizeError(c) => format!("TokenizeError: unknown character '{c}'"),
-            Self::SyntaxError(s) => format!("SyntaxError: {s}"),
-            Self::EvalError(s) => format!("EvalError: {s}"),
+            Self::TokenizeError(s, _) => format!("TokenizeError: {s}"),
+            Self::SyntaxError(s, _) => format!("SyntaxError: {s}"),
+            Self::EvalError(s, _) => format!("EvalError: {s}"),
         })
     }
 }
 
 #[cfg(test)]
-pub fn minimal_panic_hook(info: &std::panic::PanicInfo) {
+pub fn minimal_panic_hook(info: &std::panic::PanicHookInfo) {
     if let Some(msg) = info.payload().downcast_ref::<&str>() {
         println!("{msg}");
     } else if let Some(msg) = info.payload().downcast_ref::<String>() {
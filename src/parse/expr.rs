@@ -1,62 +1,640 @@
-use super::Error;
+use std::cmp::Ordering;
+use std::ops::{Add, BitAnd, BitOr, BitXor, Div, Mul, Neg, Not, Rem, Shl, Shr, Sub};
+
+use bigdecimal::BigDecimal;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+use super::decimal;
+use super::span::Span;
+use super::{Environment, Error};
+
+#[derive(Debug)]
+pub struct Expression {
+    pub kind: ExpressionKind,
+    pub span: Span,
+}
 
 #[derive(Debug)]
-pub enum Expression {
+pub enum ExpressionKind {
     Constant(Value),
+    /// Read as a float unless decimal mode is on.
+    DecimalLiteral(f64, BigDecimal),
+    Variable(String),
+    PreviousResult(usize),
+    Call(String, Vec<Expression>),
     Sum(Box<Expression>, Box<Expression>),
     Difference(Box<Expression>, Box<Expression>),
+    Product(Box<Expression>, Box<Expression>),
+    Quotient(Box<Expression>, Box<Expression>),
+    Remainder(Box<Expression>, Box<Expression>),
+    Power(Box<Expression>, Box<Expression>),
     UnaryPlus(Box<Expression>),
     UnaryMinus(Box<Expression>),
+    Equal(Box<Expression>, Box<Expression>),
+    NotEqual(Box<Expression>, Box<Expression>),
+    Less(Box<Expression>, Box<Expression>),
+    LessEqual(Box<Expression>, Box<Expression>),
+    Greater(Box<Expression>, Box<Expression>),
+    GreaterEqual(Box<Expression>, Box<Expression>),
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+    Not(Box<Expression>),
+    Factorial(Box<Expression>),
+    Percent(Box<Expression>),
+    BitwiseAnd(Box<Expression>, Box<Expression>),
+    BitwiseOr(Box<Expression>, Box<Expression>),
+    BitwiseXor(Box<Expression>, Box<Expression>),
+    BitwiseNot(Box<Expression>),
+    ShiftLeft(Box<Expression>, Box<Expression>),
+    ShiftRight(Box<Expression>, Box<Expression>),
+    /// Only the selected branch is evaluated.
+    Conditional(Box<Expression>, Box<Expression>, Box<Expression>),
+    /// Stands in for input that couldn't be parsed.
+    Invalid,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FractionForm {
+    /// Like `7/2`.
+    #[default]
+    Improper,
+    /// Like `3 1/2`.
+    Mixed,
+}
+
+/// Compares structurally, so `Integer(1) != Float(1.0)`.
+#[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     Integer(i64),
+    /// Only for integers that don't fit in an `i64`.
+    BigInteger(BigInt),
+    /// In lowest terms, with a denominator greater than 1.
+    Rational(BigRational),
+    /// The `u64` is the precision its operations round to.
+    Decimal(BigDecimal, u64),
+    Float(f64),
+    Bool(bool),
 }
 
 impl Expression {
-    pub fn eval(self) -> Result<Value, Error> {
+    pub fn new(kind: ExpressionKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    pub fn eval(&self, env: &mut Environment) -> Result<Value, Error> {
+        let result = env.enter_eval().and_then(|()| {
+            let result = self.eval_kind(env);
+            env.exit_eval();
+            result
+        });
+        result.map_err(|err| err.or_at(self.span))
+    }
+
+    fn eval_kind(&self, env: &mut Environment) -> Result<Value, Error> {
+        use ExpressionKind as Kind;
+        // Operators are applied through helper functions to keep this frame small, since it is repeated for every level of recursion.
+        match &self.kind {
+            Kind::Constant(val) => Ok(val.clone()),
+            Kind::DecimalLiteral(num, exact) => match env.decimal_precision() {
+                Some(precision) => Value::from_decimal(exact, precision),
+                None => Ok(Value::Float(*num)),
+            },
+            Kind::Variable(name) => env.get_variable(name),
+            Kind::PreviousResult(input_number) => env.get_result(*input_number).ok_or_else(|| Error::EvalError(format!("input {input_number} has no result"), None)),
+            Kind::Call(name, args) => eval_call(name, args, self.span, env),
+            Kind::Sum(left, right) => eval_binary(left, right, env, Value::add),
+            Kind::Difference(left, right) => eval_binary(left, right, env, Value::sub),
+            Kind::Product(left, right) => eval_binary(left, right, env, Value::mul),
+            Kind::Quotient(left, right) => eval_binary(left, right, env, Value::div),
+            Kind::Remainder(left, right) => eval_binary(left, right, env, Value::rem),
+            Kind::Power(left, right) => eval_power(left, right, env),
+            Kind::UnaryPlus(expr) => eval_unary(expr, env, Value::unary_plus),
+            Kind::UnaryMinus(expr) => eval_unary(expr, env, Value::neg),
+            Kind::Equal(left, right) => eval_binary(left, right, env, |lhs, rhs| lhs.compare("==", &rhs)),
+            Kind::NotEqual(left, right) => eval_binary(left, right, env, |lhs, rhs| lhs.compare("!=", &rhs)),
+            Kind::Less(left, right) => eval_binary(left, right, env, |lhs, rhs| lhs.compare("<", &rhs)),
+            Kind::LessEqual(left, right) => eval_binary(left, right, env, |lhs, rhs| lhs.compare("<=", &rhs)),
+            Kind::Greater(left, right) => eval_binary(left, right, env, |lhs, rhs| lhs.compare(">", &rhs)),
+            Kind::GreaterEqual(left, right) => eval_binary(left, right, env, |lhs, rhs| lhs.compare(">=", &rhs)),
+            Kind::And(left, right) => eval_short_circuit(left, right, env, "&&", false),
+            Kind::Or(left, right) => eval_short_circuit(left, right, env, "||", true),
+            Kind::Not(expr) => eval_unary(expr, env, Value::not),
+            Kind::Factorial(expr) => eval_unary(expr, env, Value::factorial),
+            Kind::Percent(expr) => eval_percent(expr, env),
+            Kind::BitwiseAnd(left, right) => eval_binary(left, right, env, Value::bitand),
+            Kind::BitwiseOr(left, right) => eval_binary(left, right, env, Value::bitor),
+            Kind::BitwiseXor(left, right) => eval_binary(left, right, env, Value::bitxor),
+            Kind::BitwiseNot(expr) => eval_unary(expr, env, Value::bitwise_not),
+            Kind::ShiftLeft(left, right) => eval_binary(left, right, env, Value::shl),
+            Kind::ShiftRight(left, right) => eval_binary(left, right, env, Value::shr),
+            Kind::Conditional(condition, if_true, if_false) => eval_conditional(condition, if_true, if_false, env),
+            Kind::Invalid => Err(Error::EvalError(String::from("cannot evaluate input with syntax errors"), None)),
+        }
+    }
+}
+
+const MAX_INTEGER_BITS: u64 = 1 << 20;
+
+fn eval_call(name: &str, args: &[Expression], span: Span, env: &mut Environment) -> Result<Value, Error> {
+    let args = args.iter().map(|arg| arg.eval(env)).collect::<Result<Vec<_>, _>>()?;
+    env.call(name, args).map_err(|err| err.at(span))
+}
+
+fn eval_unary(expr: &Expression, env: &mut Environment, op: fn(Value) -> Result<Value, Error>) -> Result<Value, Error> {
+    op(expr.eval(env)?)
+}
+
+fn eval_power(left: &Expression, right: &Expression, env: &mut Environment) -> Result<Value, Error> {
+    let base = left.eval(env)?;
+    base.pow(right.eval(env)?, env.decimal_precision())
+}
+
+fn eval_percent(expr: &Expression, env: &mut Environment) -> Result<Value, Error> {
+    let val = expr.eval(env)?;
+    match env.decimal_precision().and_then(|precision| Some(Value::Decimal(val.as_decimal(precision)?, precision))) {
+        Some(num) => num.percent(),
+        None => val.percent(),
+    }
+}
+
+fn eval_binary(left: &Expression, right: &Expression, env: &mut Environment, op: fn(Value, Value) -> Result<Value, Error>) -> Result<Value, Error> {
+    op(left.eval(env)?, right.eval(env)?)
+}
+
+fn eval_short_circuit(left: &Expression, right: &Expression, env: &mut Environment, op: &str, short_circuit_on: bool) -> Result<Value, Error> {
+    if left.eval(env)?.as_bool(op)? == short_circuit_on {
+        return Ok(Value::Bool(short_circuit_on));
+    }
+    Ok(Value::Bool(right.eval(env)?.as_bool(op)?))
+}
+
+fn eval_conditional(condition: &Expression, if_true: &Expression, if_false: &Expression, env: &mut Environment) -> Result<Value, Error> {
+    if condition.eval(env)?.as_bool("a condition")? {
+        if_true.eval(env)
+    } else {
+        if_false.eval(env)
+    }
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Integer(_) | Value::BigInteger(_) => "integer",
+            Value::Rational(_) => "rational",
+            Value::Decimal(..) => "decimal",
+            Value::Float(_) => "float",
+            Value::Bool(_) => "bool",
+        }
+    }
+
+    pub fn as_float(&self) -> Option<f64> {
+        match *self {
+            Value::Integer(num) => Some(num as f64),
+            Value::BigInteger(ref num) => Some(num.to_f64().unwrap_or(f64::NAN)),
+            Value::Rational(ref num) => Some(num.to_f64().unwrap_or(f64::NAN)),
+            Value::Decimal(ref num, _) => Some(num.to_f64().unwrap_or(f64::NAN)),
+            Value::Float(num) => Some(num),
+            Value::Bool(_) => None,
+        }
+    }
+
+    fn as_big_integer(&self) -> Option<BigInt> {
+        match self {
+            Value::Integer(num) => Some(BigInt::from(*num)),
+            Value::BigInteger(num) => Some(num.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn as_rational(&self) -> Option<BigRational> {
         match self {
-            Self::Constant(c) => Ok(c),
-            Self::Sum(left, right) => Ok(left.eval()? + right.eval()?),
-            Self::Difference(left, right) => Ok(left.eval()? - right.eval()?),
-            Self::UnaryPlus(expr) => Ok(expr.eval()?),
-            Self::UnaryMinus(expr) => Ok(-expr.eval()?),
+            Value::Rational(num) => Some(num.clone()),
+            Value::Decimal(num, _) => Some(decimal::to_rational(num)),
+            val => val.as_big_integer().map(BigRational::from_integer),
+        }
+    }
+
+    pub fn as_decimal(&self, precision: u64) -> Option<BigDecimal> {
+        match self {
+            Value::Decimal(num, _) => Some(num.clone()),
+            Value::Rational(num) => Some(decimal::from_rational(num, precision)),
+            val => val.as_big_integer().map(|num| BigDecimal::new(num, 0)),
+        }
+    }
+
+    /// Rounds to `precision` significant digits, failing if out of range.
+    pub fn from_decimal(num: &BigDecimal, precision: u64) -> Result<Self, Error> {
+        let num = decimal::round(num, precision);
+        if decimal::exponent(&num).abs() > decimal::MAX_EXPONENT {
+            return Err(Error::EvalError(format!("decimal result is out of range, with an exponent beyond ±{}", decimal::MAX_EXPONENT), None));
+        }
+        Ok(Value::Decimal(num, precision))
+    }
+
+    fn is_exact(&self) -> bool {
+        matches!(self, Value::Integer(_) | Value::BigInteger(_) | Value::Rational(_) | Value::Decimal(..))
+    }
+
+    fn is_exact_zero(&self) -> bool {
+        match self {
+            Value::Integer(num) => *num == 0,
+            Value::Decimal(num, _) => num.is_zero(),
+            _ => false,
+        }
+    }
+
+    /// Like `Display`, but writes rationals in the given form.
+    pub fn display(&self, form: FractionForm) -> String {
+        match (self, form) {
+            (Value::Rational(num), FractionForm::Mixed) if !num.trunc().is_zero() => format!("{} {}", num.trunc(), num.fract().abs()),
+            (val, _) => val.to_string(),
+        }
+    }
+
+    fn as_bool(&self, op: &str) -> Result<bool, Error> {
+        match *self {
+            Value::Bool(val) => Ok(val),
+            _ => Err(Error::EvalError(format!("type error: {op} expects a bool, not {}", self.type_name()), None)),
+        }
+    }
+
+    /// In decimal mode, fractional powers of exact numbers are decimals.
+    pub fn pow(self, rhs: Self, decimal_precision: Option<u64>) -> Result<Self, Error> {
+        if let (Value::Integer(base), Value::Integer(exp)) = (&self, &rhs) {
+            if let Some(num) = u32::try_from(*exp).ok().and_then(|exp| base.checked_pow(exp)) {
+                return Ok(Value::Integer(num));
+            }
+        }
+        let mode_precision = decimal_precision.filter(|_| matches!(rhs, Value::Rational(_)));
+        if let Some(precision) = operand_precision(&self, &rhs).or(mode_precision) {
+            if let (Some(base), Some(exp)) = (self.as_rational(), rhs.as_rational()) {
+                return decimal_pow(base, exp, precision).unwrap_or_else(|| Err(Error::EvalError(format!("{self}^{rhs} is not a real number"), None)));
+            }
+        }
+        match (self.as_rational(), rhs.as_big_integer()) {
+            (Some(base), Some(exp)) if base.is_zero() && exp.is_negative() => Err(Error::EvalError(String::from("division by zero"), None)),
+            (Some(base), Some(exp)) => rational_pow(base, exp).map(Value::from).ok_or_else(|| Error::EvalError(format!("{self}^{rhs} is too large"), None)),
+            _ => {
+                let (base_num, exp_num) = as_floats("^", &self, &rhs)?;
+                match base_num.powf(exp_num) {
+                    num if num.is_nan() => Err(Error::EvalError(format!("{self}^{rhs} is not a real number"), None)),
+                    num => Ok(Value::Float(num)),
+                }
+            },
+        }
+    }
+
+    pub fn unary_plus(self) -> Result<Self, Error> {
+        match self {
+            Value::Bool(_) => Err(Error::EvalError(String::from("type error: cannot apply + to bool"), None)),
+            val => Ok(val),
+        }
+    }
+
+    pub fn factorial(self) -> Result<Self, Error> {
+        match self {
+            Value::Integer(n) if n < 0 => Err(Error::EvalError(format!("factorial of negative number {n}"), None)),
+            Value::Integer(n) => match (1..=n).try_fold(1i64, |product, i| product.checked_mul(i)) {
+                Some(product) => Ok(Value::Integer(product)),
+                None => big_factorial(n),
+            },
+            Value::BigInteger(n) if n.is_negative() => Err(Error::EvalError(format!("factorial of negative number {n}"), None)),
+            Value::BigInteger(n) => Err(Error::EvalError(format!("{n}! is too large"), None)),
+            val => Err(Error::EvalError(format!("type error: cannot apply ! to {}", val.type_name()), None)),
+        }
+    }
+
+    pub fn percent(self) -> Result<Self, Error> {
+        if let Value::Decimal(num, precision) = self {
+            let (digits, scale) = num.into_bigint_and_exponent();
+            return Value::from_decimal(&BigDecimal::new(digits, scale + 2), precision);
+        }
+        match self {
+            Value::Float(val) => Ok(Value::Float(val / 100.0)),
+            val => match val.as_rational() {
+                Some(num) => Ok(Value::from(num / BigInt::from(100))),
+                None => Err(Error::EvalError(format!("type error: cannot apply % to {}", val.type_name()), None)),
+            },
+        }
+    }
+
+    pub fn bitwise_not(self) -> Result<Self, Error> {
+        match self {
+            Value::Integer(val) => Ok(Value::Integer(!val)),
+            Value::BigInteger(val) => Ok(Value::from(!val)),
+            val => Err(Error::EvalError(format!("type error: cannot apply ~ to {}", val.type_name()), None)),
+        }
+    }
+
+    /// Integers and floats with the same value are equal.
+    pub fn compare(&self, op: &str, rhs: &Self) -> Result<Self, Error> {
+        let ordering = match (self, rhs) {
+            (Value::Bool(lhs), Value::Bool(rhs)) if op == "==" || op == "!=" => Some(lhs.cmp(rhs)),
+            (lhs, rhs) if lhs.as_float().is_some() && rhs.as_float().is_some() => lhs.numeric_cmp(rhs),
+            (lhs, rhs) => return Err(type_error(op, lhs, rhs)),
+        };
+        Ok(Value::Bool(match op {
+            "==" => ordering == Some(Ordering::Equal),
+            "!=" => ordering != Some(Ordering::Equal),
+            "<" => ordering == Some(Ordering::Less),
+            "<=" => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+            ">" => ordering == Some(Ordering::Greater),
+            ">=" => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+            _ => unreachable!("{op} is not a comparison operator"),
+        }))
+    }
+
+    /// Orders numbers exactly, even against floats, so `2^53 + 1 > 2.0^53`.
+    pub fn numeric_cmp(&self, rhs: &Self) -> Option<Ordering> {
+        match (self, rhs) {
+            (Value::Integer(lhs), Value::Integer(rhs)) => Some(lhs.cmp(rhs)),
+            (lhs, rhs) => match (lhs.as_rational(), rhs.as_rational()) {
+                (Some(lhs), Some(rhs)) => Some(lhs.cmp(&rhs)),
+                (Some(lhs), None) => exact_float_cmp(&lhs, rhs.as_float()?),
+                (None, Some(rhs)) => exact_float_cmp(&rhs, lhs.as_float()?).map(Ordering::reverse),
+                (None, None) => lhs.as_float()?.partial_cmp(&rhs.as_float()?),
+            },
+        }
+    }
+}
+
+impl From<BigRational> for Value {
+    fn from(num: BigRational) -> Self {
+        if num.is_integer() {
+            Value::from(num.to_integer())
+        } else {
+            Value::Rational(num)
+        }
+    }
+}
+
+impl From<BigInt> for Value {
+    fn from(num: BigInt) -> Self {
+        match num.to_i64() {
+            Some(num) => Value::Integer(num),
+            None => Value::BigInteger(num),
+        }
+    }
+}
+
+fn exact_float_cmp(lhs: &BigRational, rhs: f64) -> Option<Ordering> {
+    match rhs {
+        f64::INFINITY => Some(Ordering::Less),
+        f64::NEG_INFINITY => Some(Ordering::Greater),
+        // Every finite float is a rational, so only `NaN` fails to convert.
+        rhs => Some(lhs.cmp(&BigRational::from_float(rhs)?)),
+    }
+}
+
+fn big_result(num: BigInt) -> Result<Value, Error> {
+    if num.bits() > MAX_INTEGER_BITS {
+        return Err(Error::EvalError(format!("integer result is too large, with more than {MAX_INTEGER_BITS} bits"), None));
+    }
+    Ok(Value::from(num))
+}
+
+fn integer_op(lhs: &Value, rhs: &Value, small: fn(i64, i64) -> Option<i64>, big: fn(BigInt, BigInt) -> BigInt) -> Option<Result<Value, Error>> {
+    if let (Value::Integer(lhs), Value::Integer(rhs)) = (lhs, rhs) {
+        if let Some(num) = small(*lhs, *rhs) {
+            return Some(Ok(Value::Integer(num)));
+        }
+    }
+    Some(big_result(big(lhs.as_big_integer()?, rhs.as_big_integer()?)))
+}
+
+fn rational_op(lhs: &Value, rhs: &Value, f: fn(BigRational, BigRational) -> BigRational) -> Option<Result<Value, Error>> {
+    let num = f(lhs.as_rational()?, rhs.as_rational()?);
+    if num.numer().bits() + num.denom().bits() > MAX_INTEGER_BITS {
+        return Some(Err(Error::EvalError(format!("rational result is too large, with more than {MAX_INTEGER_BITS} bits"), None)));
+    }
+    Some(Ok(Value::from(num)))
+}
+
+fn operand_precision(lhs: &Value, rhs: &Value) -> Option<u64> {
+    match (lhs, rhs) {
+        (Value::Decimal(_, lhs), Value::Decimal(_, rhs)) => Some(*lhs.max(rhs)),
+        (Value::Decimal(_, precision), _) | (_, Value::Decimal(_, precision)) => Some(*precision),
+        _ => None,
+    }
+}
+
+fn decimal_op(lhs: &Value, rhs: &Value, exact: fn(BigRational, BigRational) -> BigRational, f: fn(&BigDecimal, &BigDecimal, u64) -> BigDecimal) -> Option<Result<Value, Error>> {
+    let precision = operand_precision(lhs, rhs)?;
+    let num = match (lhs, rhs) {
+        (Value::Rational(_), _) | (_, Value::Rational(_)) => decimal::from_rational(&exact(lhs.as_rational()?, rhs.as_rational()?), precision),
+        _ => f(&lhs.as_decimal(precision)?, &rhs.as_decimal(precision)?, precision),
+    };
+    Some(Value::from_decimal(&num, precision))
+}
+
+fn decimal_pow(base: BigRational, exp: BigRational, precision: u64) -> Option<Result<Value, Error>> {
+    if base.is_zero() {
+        return match exp.cmp(&BigRational::zero()) {
+            Ordering::Less => Some(Err(Error::EvalError(String::from("division by zero"), None))),
+            Ordering::Equal => Some(Ok(Value::Decimal(BigDecimal::one(), precision))),
+            Ordering::Greater => Some(Ok(Value::Decimal(BigDecimal::zero(), precision))),
+        };
+    }
+    if exp.is_integer() {
+        if let Some(num) = rational_pow(base.clone(), exp.to_integer()) {
+            return Some(Value::from_decimal(&decimal::from_rational(&num, precision), precision));
+        }
+    } else if base.is_negative() {
+        return None;
+    }
+    // An odd integer power of a negative base is negative.
+    let negative = base.is_negative() && exp.to_integer().bit(0);
+    let magnitude = decimal::from_rational(&base.abs(), precision + 10);
+    let Some(num) = decimal::pow(&magnitude, &decimal::from_rational(&exp, precision + 10), precision) else {
+        return Some(Err(Error::EvalError(format!("decimal result is out of range, with an exponent beyond ±{}", decimal::MAX_EXPONENT), None)));
+    };
+    Some(Value::from_decimal(&if negative { -num } else { num }, precision))
+}
+
+fn rational_pow(base: BigRational, exp: BigInt) -> Option<BigRational> {
+    if exp.is_negative() {
+        return rational_pow(base.recip(), -exp);
+    }
+    let (numer, denom) = base.into_raw();
+    // Powers of coprime integers are coprime, so the result is already in lowest terms.
+    Some(BigRational::new_raw(integer_pow(numer, &exp)?, integer_pow(denom, &exp)?))
+}
+
+fn integer_pow(base: BigInt, exp: &BigInt) -> Option<BigInt> {
+    if base.magnitude().is_one() {
+        return Some(if exp.bit(0) { base } else { BigInt::one() });
+    }
+    if base.is_zero() {
+        return Some(if exp.is_zero() { BigInt::one() } else { base });
+    }
+    // Since `|base| >= 2`, the result has more than `exp * (bits of base - 1)` bits.
+    let exp = exp.to_u32().filter(|exp| (base.bits() - 1) * u64::from(*exp) <= MAX_INTEGER_BITS)?;
+    Some(base.pow(exp))
+}
+
+fn big_factorial(n: i64) -> Result<Value, Error> {
+    // By Stirling's approximation, `n!` has about this many bits, so huge `n` fails without computing anything.
+    let bits = n as f64 * (n as f64 / std::f64::consts::E).log2() + 0.5 * (std::f64::consts::TAU * n as f64).log2();
+    if bits > MAX_INTEGER_BITS as f64 {
+        return Err(Error::EvalError(format!("{n}! is too large"), None));
+    }
+    big_result((1..=n).fold(BigInt::one(), |product, i| product * i))
+}
+
+fn bitwise(op: &str, lhs: &Value, rhs: &Value, small: fn(i64, i64) -> i64, big: fn(BigInt, BigInt) -> BigInt) -> Result<Value, Error> {
+    match (lhs, rhs) {
+        (Value::Integer(lhs), Value::Integer(rhs)) => Ok(Value::Integer(small(*lhs, *rhs))),
+        _ => match (lhs.as_big_integer(), rhs.as_big_integer()) {
+            (Some(lhs), Some(rhs)) => Ok(Value::from(big(lhs, rhs))),
+            _ => Err(type_error(op, lhs, rhs)),
+        },
+    }
+}
+
+fn shift(op: &str, lhs: &Value, rhs: &Value, small: fn(i64, u32) -> Option<i64>, big: fn(BigInt, u64) -> Result<Value, Error>) -> Result<Value, Error> {
+    let (Some(num), Some(amount)) = (lhs.as_big_integer(), rhs.as_big_integer()) else {
+        return Err(type_error(op, lhs, rhs));
+    };
+    if amount.is_negative() {
+        return Err(Error::EvalError(format!("cannot shift by {rhs}, which is negative"), None));
+    }
+    if let (Value::Integer(lhs), Some(amount)) = (lhs, amount.to_u32()) {
+        if let Some(num) = small(*lhs, amount) {
+            return Ok(Value::Integer(num));
         }
     }
+    big(num, amount.to_u64().unwrap_or(u64::MAX))
+}
+
+fn type_error(op: &str, lhs: &Value, rhs: &Value) -> Error {
+    Error::EvalError(format!("type error: cannot apply {op} to {} and {}", lhs.type_name(), rhs.type_name()), None)
+}
+
+fn as_floats(op: &str, lhs: &Value, rhs: &Value) -> Result<(f64, f64), Error> {
+    match (lhs.as_float(), rhs.as_float()) {
+        (Some(lhs), Some(rhs)) => Ok((lhs, rhs)),
+        _ => Err(type_error(op, lhs, rhs)),
+    }
 }
 
 impl std::fmt::Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(&match self {
             Self::Integer(num) => format!("{num}"),
+            Self::BigInteger(num) => format!("{num}"),
+            Self::Rational(num) => format!("{num}"),
+            Self::Decimal(num, _) => decimal::format(num),
+            Self::Float(num) => format!("{num:?}"),
+            Self::Bool(val) => format!("{val}"),
         })
     }
 }
 
 impl std::ops::Add for Value {
-    type Output = Self;
+    type Output = Result<Self, Error>;
     fn add(self, rhs: Self) -> Self::Output {
-        match (self, rhs) {
-            (Value::Integer(lhs), Value::Integer(rhs)) => Value::Integer(lhs + rhs),
-        }
+        integer_op(&self, &rhs, i64::checked_add, |lhs, rhs| lhs + rhs).or_else(|| decimal_op(&self, &rhs, |lhs, rhs| lhs + rhs, |lhs, rhs, _| lhs + rhs)).or_else(|| rational_op(&self, &rhs, |lhs, rhs| lhs + rhs)).unwrap_or_else(|| as_floats("+", &self, &rhs).map(|(lhs, rhs)| Value::Float(lhs + rhs)))
     }
 }
 
 impl std::ops::Sub for Value {
-    type Output = Self;
+    type Output = Result<Self, Error>;
     fn sub(self, rhs: Self) -> Self::Output {
-        match (self, rhs) {
-            (Value::Integer(lhs), Value::Integer(rhs)) => Value::Integer(lhs - rhs),
+        integer_op(&self, &rhs, i64::checked_sub, |lhs, rhs| lhs - rhs).or_else(|| decimal_op(&self, &rhs, |lhs, rhs| lhs - rhs, |lhs, rhs, _| lhs - rhs)).or_else(|| rational_op(&self, &rhs, |lhs, rhs| lhs - rhs)).unwrap_or_else(|| as_floats("-", &self, &rhs).map(|(lhs, rhs)| Value::Float(lhs - rhs)))
+    }
+}
+
+impl std::ops::Mul for Value {
+    type Output = Result<Self, Error>;
+    fn mul(self, rhs: Self) -> Self::Output {
+        integer_op(&self, &rhs, i64::checked_mul, |lhs, rhs| lhs * rhs).or_else(|| decimal_op(&self, &rhs, |lhs, rhs| lhs * rhs, |lhs, rhs, _| lhs * rhs)).or_else(|| rational_op(&self, &rhs, |lhs, rhs| lhs * rhs)).unwrap_or_else(|| as_floats("*", &self, &rhs).map(|(lhs, rhs)| Value::Float(lhs * rhs)))
+    }
+}
+
+impl std::ops::Div for Value {
+    type Output = Result<Self, Error>;
+    fn div(self, rhs: Self) -> Self::Output {
+        match (&self, &rhs) {
+            (lhs, rhs) if lhs.is_exact() && rhs.is_exact_zero() => Err(Error::EvalError(String::from("division by zero"), None)),
+            // Integers that don't divide evenly give a rational.
+            (Value::Integer(lhs), Value::Integer(rhs)) if lhs.checked_rem(*rhs) == Some(0) => Ok(Value::Integer(lhs / rhs)),
+            _ => decimal_op(&self, &rhs, |lhs, rhs| lhs / rhs, decimal::divide).or_else(|| rational_op(&self, &rhs, |lhs, rhs| lhs / rhs)).unwrap_or_else(|| match as_floats("/", &self, &rhs)? {
+                (_, 0.0) => Err(Error::EvalError(String::from("division by zero"), None)),
+                (lhs, rhs) => Ok(Value::Float(lhs / rhs)),
+            }),
+        }
+    }
+}
+
+impl std::ops::Rem for Value {
+    type Output = Result<Self, Error>;
+    fn rem(self, rhs: Self) -> Self::Output {
+        match (&self, &rhs) {
+            (lhs, rhs) if lhs.is_exact() && rhs.is_exact_zero() => Err(Error::EvalError(String::from("modulo by zero"), None)),
+            // Only `i64::MIN % -1` wraps, and its result of 0 is correct.
+            _ => integer_op(&self, &rhs, |lhs, rhs| Some(lhs.wrapping_rem(rhs)), |lhs, rhs| lhs % rhs).or_else(|| decimal_op(&self, &rhs, |lhs, rhs| lhs % rhs, |lhs, rhs, _| lhs % rhs)).or_else(|| rational_op(&self, &rhs, |lhs, rhs| lhs % rhs)).unwrap_or_else(|| match as_floats("%", &self, &rhs)? {
+                (_, 0.0) => Err(Error::EvalError(String::from("modulo by zero"), None)),
+                (lhs, rhs) => Ok(Value::Float(lhs % rhs)),
+            }),
         }
     }
 }
 
 impl std::ops::Neg for Value {
-    type Output = Self;
+    type Output = Result<Self, Error>;
     fn neg(self) -> Self::Output {
         match self {
-            Value::Integer(val) => Value::Integer(-val),
+            Value::Integer(val) => Ok(val.checked_neg().map_or_else(|| Value::from(-BigInt::from(val)), Value::Integer)),
+            Value::BigInteger(val) => Ok(Value::from(-val)),
+            Value::Rational(val) => Ok(Value::Rational(-val)),
+            Value::Decimal(val, precision) => Ok(Value::Decimal(-val, precision)),
+            Value::Float(val) => Ok(Value::Float(-val)),
+            Value::Bool(_) => Err(Error::EvalError(String::from("type error: cannot apply - to bool"), None)),
         }
     }
 }
+
+impl std::ops::Not for Value {
+    type Output = Result<Self, Error>;
+    fn not(self) -> Self::Output {
+        Ok(Value::Bool(!self.as_bool("!")?))
+    }
+}
+
+impl std::ops::BitAnd for Value {
+    type Output = Result<Self, Error>;
+    fn bitand(self, rhs: Self) -> Self::Output {
+        bitwise("&", &self, &rhs, |lhs, rhs| lhs & rhs, |lhs, rhs| lhs & rhs)
+    }
+}
+
+impl std::ops::BitOr for Value {
+    type Output = Result<Self, Error>;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        bitwise("|", &self, &rhs, |lhs, rhs| lhs | rhs, |lhs, rhs| lhs | rhs)
+    }
+}
+
+impl std::ops::BitXor for Value {
+    type Output = Result<Self, Error>;
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        bitwise("xor", &self, &rhs, |lhs, rhs| lhs ^ rhs, |lhs, rhs| lhs ^ rhs)
+    }
+}
+
+impl std::ops::Shl for Value {
+    type Output = Result<Self, Error>;
+    fn shl(self, rhs: Self) -> Self::Output {
+        // Shifting back checks that no bits were lost. Capping the amount keeps e.g. `1 << 2^100` from allocating, while still being too large for `big_result`.
+        shift("<<", &self, &rhs, |lhs, rhs| lhs.checked_shl(rhs).filter(|num| num >> rhs == lhs), |lhs, rhs| big_result(lhs << rhs.min(MAX_INTEGER_BITS + 1)))
+    }
+}
+
+impl std::ops::Shr for Value {
+    type Output = Result<Self, Error>;
+    fn shr(self, rhs: Self) -> Self::Output {
+        shift(">>", &self, &rhs, |lhs, rhs| Some(lhs >> rhs.min(i64::BITS - 1)), |lhs, rhs| Ok(Value::from(lhs >> rhs)))
+    }
+}
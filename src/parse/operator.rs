@@ -0,0 +1,72 @@
+use super::expr::{Expression, ExpressionKind};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Associativity {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Fixity {
+    Prefix(fn(Box<Expression>) -> ExpressionKind),
+    Postfix(fn(Box<Expression>) -> ExpressionKind),
+    Infix(Associativity, fn(Box<Expression>, Box<Expression>) -> ExpressionKind),
+    Ternary(&'static str, fn(Box<Expression>, Box<Expression>, Box<Expression>) -> ExpressionKind),
+}
+
+#[derive(Debug)]
+pub struct Operator {
+    pub symbol: &'static str,
+    pub binding_power: u8,
+    pub fixity: Fixity,
+}
+
+#[rustfmt::skip]
+const OPERATORS: &[Operator] = &[
+    Operator { symbol: "?", binding_power: 1, fixity: Fixity::Ternary(":", ExpressionKind::Conditional) },
+    Operator { symbol: "||", binding_power: 2, fixity: Fixity::Infix(Associativity::Left, ExpressionKind::Or) },
+    Operator { symbol: "&&", binding_power: 3, fixity: Fixity::Infix(Associativity::Left, ExpressionKind::And) },
+    Operator { symbol: "==", binding_power: 4, fixity: Fixity::Infix(Associativity::Left, ExpressionKind::Equal) },
+    Operator { symbol: "!=", binding_power: 4, fixity: Fixity::Infix(Associativity::Left, ExpressionKind::NotEqual) },
+    Operator { symbol: "<", binding_power: 4, fixity: Fixity::Infix(Associativity::Left, ExpressionKind::Less) },
+    Operator { symbol: "<=", binding_power: 4, fixity: Fixity::Infix(Associativity::Left, ExpressionKind::LessEqual) },
+    Operator { symbol: ">", binding_power: 4, fixity: Fixity::Infix(Associativity::Left, ExpressionKind::Greater) },
+    Operator { symbol: ">=", binding_power: 4, fixity: Fixity::Infix(Associativity::Left, ExpressionKind::GreaterEqual) },
+    Operator { symbol: "|", binding_power: 5, fixity: Fixity::Infix(Associativity::Left, ExpressionKind::BitwiseOr) },
+    Operator { symbol: "xor", binding_power: 6, fixity: Fixity::Infix(Associativity::Left, ExpressionKind::BitwiseXor) },
+    Operator { symbol: "&", binding_power: 7, fixity: Fixity::Infix(Associativity::Left, ExpressionKind::BitwiseAnd) },
+    Operator { symbol: "<<", binding_power: 8, fixity: Fixity::Infix(Associativity::Left, ExpressionKind::ShiftLeft) },
+    Operator { symbol: ">>", binding_power: 8, fixity: Fixity::Infix(Associativity::Left, ExpressionKind::ShiftRight) },
+    Operator { symbol: "+", binding_power: 10, fixity: Fixity::Infix(Associativity::Left, ExpressionKind::Sum) },
+    Operator { symbol: "-", binding_power: 10, fixity: Fixity::Infix(Associativity::Left, ExpressionKind::Difference) },
+    Operator { symbol: "*", binding_power: 20, fixity: Fixity::Infix(Associativity::Left, ExpressionKind::Product) },
+    Operator { symbol: "/", binding_power: 20, fixity: Fixity::Infix(Associativity::Left, ExpressionKind::Quotient) },
+    Operator { symbol: "%", binding_power: 20, fixity: Fixity::Infix(Associativity::Left, ExpressionKind::Remainder) },
+    Operator { symbol: "+", binding_power: 30, fixity: Fixity::Prefix(ExpressionKind::UnaryPlus) },
+    Operator { symbol: "-", binding_power: 30, fixity: Fixity::Prefix(ExpressionKind::UnaryMinus) },
+    Operator { symbol: "!", binding_power: 30, fixity: Fixity::Prefix(ExpressionKind::Not) },
+    Operator { symbol: "~", binding_power: 30, fixity: Fixity::Prefix(ExpressionKind::BitwiseNot) },
+    Operator { symbol: "√", binding_power: 30, fixity: Fixity::Prefix(|operand| ExpressionKind::Call(String::from("sqrt"), vec![*operand])) },
+    Operator { symbol: "^", binding_power: 40, fixity: Fixity::Infix(Associativity::Right, ExpressionKind::Power) },
+    Operator { symbol: "**", binding_power: 40, fixity: Fixity::Infix(Associativity::Right, ExpressionKind::Power) },
+    Operator { symbol: "!", binding_power: 50, fixity: Fixity::Postfix(ExpressionKind::Factorial) },
+    Operator { symbol: "%", binding_power: 50, fixity: Fixity::Postfix(ExpressionKind::Percent) },
+];
+
+pub const JUXTAPOSITION: Operator = Operator { symbol: "", binding_power: 20, fixity: Fixity::Infix(Associativity::Left, ExpressionKind::Product) };
+
+pub fn find_prefix(symbol: &str) -> Option<&'static Operator> {
+    OPERATORS.iter().find(|op| op.symbol == symbol && matches!(op.fixity, Fixity::Prefix(_)))
+}
+
+pub fn find_postfix(symbol: &str) -> Option<&'static Operator> {
+    OPERATORS.iter().find(|op| op.symbol == symbol && matches!(op.fixity, Fixity::Postfix(_)))
+}
+
+pub fn find_infix(symbol: &str) -> Option<&'static Operator> {
+    OPERATORS.iter().find(|op| op.symbol == symbol && matches!(op.fixity, Fixity::Infix(..) | Fixity::Ternary(..)))
+}
+
+pub fn is_sign(symbol: &str) -> bool {
+    symbol == "+" || symbol == "-"
+}
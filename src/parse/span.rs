@@ -0,0 +1,23 @@
+/// Byte offsets into the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// The smallest span covering both `self` and `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+
+    pub fn underline(self, input: &str) -> String {
+        let indent = input[..self.start].chars().count();
+        let width = input[self.start..self.end].chars().count().max(1);
+        format!("  {input}\n  {}{}", " ".repeat(indent), "^".repeat(width))
+    }
+}
@@ -0,0 +1,37 @@
+use std::rc::Rc;
+
+use super::environment::Function;
+use super::{Environment, Error, Expression, Value};
+
+#[derive(Debug)]
+pub enum Statement {
+    Expression(Expression),
+    Assignment(String, Expression),
+    FunctionDefinition(String, Vec<String>, Expression),
+    /// The result is that of the last one.
+    Sequence(Vec<Statement>),
+}
+
+impl Statement {
+    pub fn execute(self, env: &mut Environment) -> Result<Option<Value>, Error> {
+        match self {
+            Self::Expression(expr) => Ok(Some(expr.eval(env)?)),
+            Self::Assignment(name, expr) => {
+                let val = expr.eval(env)?;
+                env.set_variable(name, val.clone())?;
+                Ok(Some(val))
+            },
+            Self::FunctionDefinition(name, params, body) => {
+                env.set_function(name, Rc::new(Function { params, body }));
+                Ok(None)
+            },
+            Self::Sequence(statements) => {
+                let mut result = None;
+                for statement in statements {
+                    result = statement.execute(env)?;
+                }
+                Ok(result)
+            },
+        }
+    }
+}
@@ -1,19 +1,37 @@
+use super::span::Span;
 use super::Error;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Token {
     Identifier(String),
     WholeNumber(String),
+    Decimal(String),
+    RadixNumber(String),
+    /// Like `$3` or `_3`.
+    PreviousResult(String),
     Operator(String),
     Space,
+    /// Unrecognized text, kept so that parsing can go on.
+    Invalid(String),
 }
 
+#[rustfmt::skip]
+const SYMBOLS: &[&str] = &[
+    "**", "==", "!=", "<=", ">=", "&&", "||", "<<", ">>", "->", "//",
+    "+", "-", "*", "/", "%", "^", "!", "~", "&", "|", "<", ">", "=", "?", ":", "(", ")", ",", ".", ";", "√",
+];
+
+const SUPERSCRIPT_DIGITS: &str = "⁰¹²³⁴⁵⁶⁷⁸⁹";
+
+#[derive(Clone, Copy)]
 enum Char {
     Digit(char),
     Letter(char),
     Symbol(char),
     Space,
     Unknown(char),
+    Name(&'static str),
+    Superscript(char),
 }
 
 impl Token {
@@ -21,14 +39,30 @@ impl Token {
         match self {
             Token::Identifier(s) => s,
             Token::WholeNumber(s) => s,
+            Token::Decimal(s) => s,
+            Token::RadixNumber(s) => s,
+            Token::PreviousResult(s) => s,
             Token::Operator(s) => s,
             Token::Space => " ",
+            Token::Invalid(s) => s,
         }
     }
 }
 
 impl Char {
     fn new(c: char) -> Char {
+        if let Some(digit) = SUPERSCRIPT_DIGITS.chars().position(|superscript| superscript == c) {
+            return Char::Superscript(char::from(b'0' + digit as u8));
+        }
+        match c {
+            '×' => return Char::Symbol('*'),
+            '÷' => return Char::Symbol('/'),
+            '−' => return Char::Symbol('-'),
+            '√' => return Char::Symbol('√'),
+            'π' => return Char::Name("pi"),
+            '⁻' => return Char::Superscript('-'),
+            _ => (),
+        }
         if c.is_ascii_alphabetic() {
             Char::Letter(c)
         } else if c.is_ascii_digit() {
@@ -43,34 +77,144 @@ impl Char {
     }
 }
 
-pub fn tokenize(expr: &str) -> Result<Vec<Token>, Error> {
+pub fn tokenize(expr: &str) -> Result<Vec<(Token, Span)>, Error> {
+    let (tokens, errors) = tokenize_recovering(expr);
+    match errors.into_iter().next() {
+        Some(err) => Err(err),
+        None => Ok(tokens),
+    }
+}
+
+pub fn tokenize_recovering(expr: &str) -> (Vec<(Token, Span)>, Vec<Error>) {
+    let mut errors = Vec::new();
     let mut tokens = Vec::new();
-    for c in expr.chars().map(Char::new) {
-        match (tokens.last_mut(), c) {
-            (_, Char::Unknown(c)) => return Err(Error::TokenizeError(c)),
+    let mut spans: Vec<Span> = Vec::new();
+    let mut in_comment = false;
+    let mut prev_c = None;
+    for (i, raw_c) in expr.char_indices() {
+        // A `#` starts a comment, which runs to the end of the line.
+        in_comment = (in_comment || raw_c == '#') && raw_c != '\n';
+        if in_comment {
+            continue;
+        }
+        let c_span = Span::new(i, i + raw_c.len_utf8());
+        let c = Char::new(raw_c);
+        let after_superscript = matches!(prev_c, Some(Char::Superscript(_)));
+        // Nothing joins a name like `π`, so `πr` is `pi` then `r`. Only superscripts join superscripts, so `x²3` isn't `x^23`.
+        let last = match prev_c {
+            Some(Char::Name(_)) => None,
+            Some(Char::Superscript(_)) if !matches!(c, Char::Superscript(_)) => None,
+            _ => tokens.last_mut(),
+        };
+        prev_c = Some(c);
+        match (last, c) {
+            (_, Char::Unknown(c)) => {
+                errors.push(Error::TokenizeError(format!("unknown character '{c}'"), c_span));
+                tokens.push(Token::Invalid(String::from(c)));
+            },
             (Some(Token::Space), Char::Space) => (),
             (_, Char::Space) => tokens.push(Token::Space),
             (Some(Token::Identifier(s)), Char::Letter(c)) => s.push(c),
             (Some(Token::Identifier(s)), Char::Digit(c)) => s.push(c),
+            (Some(Token::Identifier(s)), Char::Symbol('_')) => s.push('_'),
             (Some(Token::WholeNumber(s)), Char::Digit(c)) => s.push(c),
+            (Some(Token::Decimal(s)), Char::Digit(c)) => s.push(c),
+            (Some(tok @ Token::WholeNumber(_)), Char::Letter(c @ ('x' | 'X' | 'o' | 'O' | 'b' | 'B'))) if tok.to_str() == "0" => *tok = Token::RadixNumber(format!("0{c}")),
+            (Some(Token::RadixNumber(s)), Char::Digit(c) | Char::Letter(c)) => s.push(c),
+            (Some(Token::WholeNumber(s) | Token::Decimal(s) | Token::RadixNumber(s)), Char::Symbol('_')) => s.push('_'),
+            (Some(tok @ Token::WholeNumber(_)), Char::Letter(c @ ('e' | 'E'))) => *tok = Token::Decimal(format!("{}{c}", tok.to_str())),
+            (Some(Token::Decimal(s)), Char::Letter(c @ ('e' | 'E'))) if !s.contains(['e', 'E']) => s.push(c),
+            // Letters after an exponent can't start an implicit product, since `1e3e4` is more likely a typo.
+            (Some(Token::Decimal(s)), Char::Letter(c)) if s.contains(['e', 'E']) => s.push(c),
+            (Some(Token::Decimal(s)), Char::Symbol(c @ ('+' | '-'))) if s.ends_with(['e', 'E']) => s.push(c),
+            (Some(tok @ Token::WholeNumber(_)), Char::Symbol('.')) => *tok = Token::Decimal(format!("{}.", tok.to_str())),
+            (Some(tok @ Token::Operator(_)), Char::Digit(c)) if tok.to_str() == "." => *tok = Token::Decimal(format!(".{c}")),
+            (Some(Token::PreviousResult(s)), Char::Digit(c)) => s.push(c),
+            (_, Char::Symbol(c @ ('$' | '_'))) => tokens.push(Token::PreviousResult(String::from(c))),
             (_, Char::Digit(c)) => tokens.push(Token::WholeNumber(String::from(c))),
             (_, Char::Letter(c)) => tokens.push(Token::Identifier(String::from(c))),
             (_, Char::Symbol(c)) => tokens.push(Token::Operator(String::from(c))),
+            (_, Char::Name(name)) => tokens.push(Token::Identifier(String::from(name))),
+            (Some(Token::WholeNumber(s)), Char::Superscript(c)) if after_superscript && c != '-' => s.push(c),
+            (Some(tok @ Token::Operator(_)), Char::Superscript(c)) if after_superscript && c != '-' && tok.to_str() == "-" => tokens.push(Token::WholeNumber(String::from(c))),
+            // A superscript starts an exponent, so `x²` is `x^2` and `x⁻¹` is `x^-1`.
+            (_, Char::Superscript(c)) => {
+                tokens.push(Token::Operator(String::from("^")));
+                tokens.push(if c == '-' { Token::Operator(String::from("-")) } else { Token::WholeNumber(String::from(c)) });
+            },
+        }
+        // Each character either extends the last token or starts new ones.
+        if spans.len() == tokens.len() {
+            if let Some(span) = spans.last_mut() {
+                span.end = c_span.end;
+            }
+        }
+        while spans.len() < tokens.len() {
+            spans.push(c_span);
+        }
+    }
+    let tokens = munch_operators(tokens.into_iter().zip(spans).collect(), &mut errors);
+    errors.sort_by_key(|err| err.span().map(|span| span.start));
+    (tokens, errors)
+}
+
+fn munch_operators(tokens: Vec<(Token, Span)>, errors: &mut Vec<Error>) -> Vec<(Token, Span)> {
+    let mut munched = Vec::new();
+    let mut run = Vec::new();
+    for (tok, span) in tokens {
+        match tok {
+            Token::Operator(symbol) => run.extend(symbol.chars().map(|c| (c, span))),
+            tok => {
+                split_symbols(&std::mem::take(&mut run), &mut munched, errors);
+                munched.push((tok, span));
+            },
+        }
+    }
+    split_symbols(&run, &mut munched, errors);
+    munched
+}
+
+fn longest_symbol(chars: &[char]) -> Option<&'static str> {
+    SYMBOLS.iter().copied().filter(|symbol| symbol.chars().count() <= chars.len() && symbol.chars().zip(chars).all(|(a, &b)| a == b)).max_by_key(|symbol| symbol.len())
+}
+
+fn split_symbols(run: &[(char, Span)], tokens: &mut Vec<(Token, Span)>, errors: &mut Vec<Error>) {
+    let chars: Vec<char> = run.iter().map(|(c, _)| *c).collect();
+    let mut pos = 0;
+    while pos < chars.len() {
+        let len = match longest_symbol(&chars[pos..]) {
+            Some(symbol) => symbol.chars().count(),
+            // Take everything up to the next known symbol, so ``@` `` is named as a whole.
+            None => (pos + 1..chars.len()).find(|&i| longest_symbol(&chars[i..]).is_some()).unwrap_or(chars.len()) - pos,
+        };
+        let text: String = chars[pos..pos + len].iter().collect();
+        let span = run[pos].1.to(run[pos + len - 1].1);
+        if longest_symbol(&chars[pos..]).is_some() {
+            tokens.push((Token::Operator(text), span));
+        } else {
+            errors.push(Error::TokenizeError(format!("unknown operator \"{text}\""), span));
+            tokens.push((Token::Invalid(text), span));
         }
+        pos += len;
     }
-    Ok(tokens)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn tokenize(expr: &str) -> Result<Vec<Token>, Error> {
+        Ok(super::tokenize(expr)?.into_iter().map(|(tok, _)| tok).collect())
+    }
+
     #[test]
     fn tokenize_identifier() -> Result<(), Error> {
         assert_eq!(tokenize("x")?, vec![Token::Identifier(String::from("x"))]);
         assert_eq!(tokenize("xy")?, vec![Token::Identifier(String::from("xy"))]);
         assert_eq!(tokenize("z23")?, vec![Token::Identifier(String::from("z23"))]);
         assert_eq!(tokenize("ffff23wwww")?, vec![Token::Identifier(String::from("ffff23wwww"))]);
+        assert_eq!(tokenize("N_A")?, vec![Token::Identifier(String::from("N_A"))]);
+        assert_eq!(tokenize("a_b_")?, vec![Token::Identifier(String::from("a_b_"))]);
         assert_ne!(tokenize("_")?, vec![Token::Identifier(String::from("_"))]);
         assert_ne!(tokenize("4a")?, vec![Token::Identifier(String::from("4a"))]);
         Ok(())
@@ -86,15 +230,146 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn tokenize_decimal() -> Result<(), Error> {
+        assert_eq!(tokenize("3.14")?, vec![Token::Decimal(String::from("3.14"))]);
+        assert_eq!(tokenize(".5")?, vec![Token::Decimal(String::from(".5"))]);
+        assert_eq!(tokenize("2.")?, vec![Token::Decimal(String::from("2."))]);
+        assert_eq!(tokenize("0.000")?, vec![Token::Decimal(String::from("0.000"))]);
+        assert_eq!(tokenize("1.2.3")?, vec![Token::Decimal(String::from("1.2")), Token::Decimal(String::from(".3"))]);
+        assert_ne!(tokenize(". 5")?, vec![Token::Decimal(String::from(".5"))]);
+        Ok(())
+    }
+
+    #[test]
+    fn tokenize_scientific() -> Result<(), Error> {
+        assert_eq!(tokenize("6.022e23")?, vec![Token::Decimal(String::from("6.022e23"))]);
+        assert_eq!(tokenize("1E-9")?, vec![Token::Decimal(String::from("1E-9"))]);
+        assert_eq!(tokenize("5e+3")?, vec![Token::Decimal(String::from("5e+3"))]);
+        assert_eq!(tokenize(".5e3")?, vec![Token::Decimal(String::from(".5e3"))]);
+        assert_eq!(tokenize("1e")?, vec![Token::Decimal(String::from("1e"))]);
+        assert_eq!(tokenize("1e+")?, vec![Token::Decimal(String::from("1e+"))]);
+        assert_eq!(tokenize("1e3-2")?, vec![Token::Decimal(String::from("1e3")), Token::Operator(String::from("-")), Token::WholeNumber(String::from("2"))]);
+        assert_eq!(tokenize("1e3e4")?, vec![Token::Decimal(String::from("1e3e4"))]);
+        assert_eq!(tokenize("2.5x")?, vec![Token::Decimal(String::from("2.5")), Token::Identifier(String::from("x"))]);
+        Ok(())
+    }
+
+    #[test]
+    fn tokenize_radix_number() -> Result<(), Error> {
+        assert_eq!(tokenize("0xFF")?, vec![Token::RadixNumber(String::from("0xFF"))]);
+        assert_eq!(tokenize("0o755")?, vec![Token::RadixNumber(String::from("0o755"))]);
+        assert_eq!(tokenize("0b1010_0101")?, vec![Token::RadixNumber(String::from("0b1010_0101"))]);
+        assert_eq!(tokenize("0X1e")?, vec![Token::RadixNumber(String::from("0X1e"))]);
+        assert_eq!(tokenize("0x")?, vec![Token::RadixNumber(String::from("0x"))]);
+        assert_ne!(tokenize("1xFF")?, vec![Token::RadixNumber(String::from("1xFF"))]);
+        assert_ne!(tokenize("00xFF")?, vec![Token::RadixNumber(String::from("00xFF"))]);
+        Ok(())
+    }
+
+    #[test]
+    fn tokenize_digit_separator() -> Result<(), Error> {
+        assert_eq!(tokenize("1_000_000")?, vec![Token::WholeNumber(String::from("1_000_000"))]);
+        assert_eq!(tokenize("3.141_592")?, vec![Token::Decimal(String::from("3.141_592"))]);
+        assert_eq!(tokenize("1_")?, vec![Token::WholeNumber(String::from("1_"))]);
+        assert_ne!(tokenize("_1")?, vec![Token::WholeNumber(String::from("_1"))]);
+        Ok(())
+    }
+
+    #[test]
+    fn tokenize_previous_result() -> Result<(), Error> {
+        assert_eq!(tokenize("$1")?, vec![Token::PreviousResult(String::from("$1"))]);
+        assert_eq!(tokenize("_12")?, vec![Token::PreviousResult(String::from("_12"))]);
+        assert_eq!(tokenize("$")?, vec![Token::PreviousResult(String::from("$"))]);
+        assert_eq!(tokenize("$1a")?, vec![Token::PreviousResult(String::from("$1")), Token::Identifier(String::from("a"))]);
+        assert_ne!(tokenize("a_1")?, vec![Token::Identifier(String::from("a")), Token::PreviousResult(String::from("_1"))]);
+        Ok(())
+    }
+
     #[test]
     fn tokenize_operator() -> Result<(), Error> {
         assert_eq!(tokenize("+")?, vec![Token::Operator(String::from("+"))]);
         assert_eq!(tokenize("+-")?, vec![Token::Operator(String::from("+")), Token::Operator(String::from("-"))]);
         assert_eq!(tokenize("((")?, vec![Token::Operator(String::from("(")), Token::Operator(String::from("("))]);
         assert_eq!(tokenize(".")?, vec![Token::Operator(String::from("."))]);
+        assert_eq!(tokenize("**")?, vec![Token::Operator(String::from("**"))]);
+        assert_eq!(tokenize("<=")?, vec![Token::Operator(String::from("<="))]);
+        assert_eq!(tokenize("!=!")?, vec![Token::Operator(String::from("!=")), Token::Operator(String::from("!"))]);
+        assert_eq!(tokenize("&&&")?, vec![Token::Operator(String::from("&&")), Token::Operator(String::from("&"))]);
+        assert_eq!(tokenize("1<<2>>3")?, vec![Token::WholeNumber(String::from("1")), Token::Operator(String::from("<<")), Token::WholeNumber(String::from("2")), Token::Operator(String::from(">>")), Token::WholeNumber(String::from("3"))]);
+        assert_eq!(tokenize("=<")?, vec![Token::Operator(String::from("=")), Token::Operator(String::from("<"))]);
+        assert_eq!(tokenize("* *")?, vec![Token::Operator(String::from("*")), Token::Space, Token::Operator(String::from("*"))]);
+        assert_eq!(tokenize("->")?, vec![Token::Operator(String::from("->"))]);
+        assert_eq!(tokenize("//")?, vec![Token::Operator(String::from("//"))]);
+        assert_eq!(tokenize("***")?, vec![Token::Operator(String::from("**")), Token::Operator(String::from("*"))]);
+        assert_eq!(tokenize("<<=")?, vec![Token::Operator(String::from("<<")), Token::Operator(String::from("="))]);
+        assert_eq!(tokenize("<==")?, vec![Token::Operator(String::from("<=")), Token::Operator(String::from("="))]);
+        assert_eq!(tokenize("-->")?, vec![Token::Operator(String::from("-")), Token::Operator(String::from("->"))]);
+        assert_eq!(tokenize("1+.5")?, vec![Token::WholeNumber(String::from("1")), Token::Operator(String::from("+")), Token::Decimal(String::from(".5"))]);
+        Ok(())
+    }
+
+    #[test]
+    fn tokenize_comment() -> Result<(), Error> {
+        let s = String::from;
+        assert_eq!(tokenize("# comment")?, vec![]);
+        assert_eq!(tokenize("1 # one")?, vec![Token::WholeNumber(s("1")), Token::Space]);
+        assert_eq!(tokenize("1# @é\n2")?, vec![Token::WholeNumber(s("1")), Token::Space, Token::WholeNumber(s("2"))]);
+        assert_eq!(tokenize("a;b")?, vec![Token::Identifier(s("a")), Token::Operator(s(";")), Token::Identifier(s("b"))]);
         Ok(())
     }
 
+    #[test]
+    fn tokenize_spans() -> Result<(), Error> {
+        let spans = |expr| -> Result<Vec<(usize, usize)>, Error> { Ok(super::tokenize(expr)?.into_iter().map(|(_, span)| (span.start, span.end)).collect()) };
+        assert_eq!(spans("12 + x")?, vec![(0, 2), (2, 3), (3, 4), (4, 5), (5, 6)]);
+        assert_eq!(spans("1<<=2.5e-3")?, vec![(0, 1), (1, 3), (3, 4), (4, 10)]);
+        assert_eq!(spans(".5  $12")?, vec![(0, 2), (2, 4), (4, 7)]);
+        assert_eq!(spans("0x1F!")?, vec![(0, 4), (4, 5)]);
+        assert_eq!(spans("1 # 2\n3")?, vec![(0, 1), (1, 6), (6, 7)]);
+        Ok(())
+    }
+
+    #[test]
+    fn tokenize_unicode() -> Result<(), Error> {
+        let s = String::from;
+        let op = |symbol| Token::Operator(s(symbol));
+        assert_eq!(tokenize("2×3÷4−1")?, vec![Token::WholeNumber(s("2")), op("*"), Token::WholeNumber(s("3")), op("/"), Token::WholeNumber(s("4")), op("-"), Token::WholeNumber(s("1"))]);
+        assert_eq!(tokenize("√2")?, vec![op("√"), Token::WholeNumber(s("2"))]);
+        assert_eq!(tokenize("2πr")?, vec![Token::WholeNumber(s("2")), Token::Identifier(s("pi")), Token::Identifier(s("r"))]);
+        assert_eq!(tokenize("x²³")?, vec![Token::Identifier(s("x")), op("^"), Token::WholeNumber(s("23"))]);
+        assert_eq!(tokenize("x⁻¹")?, vec![Token::Identifier(s("x")), op("^"), op("-"), Token::WholeNumber(s("1"))]);
+        assert_eq!(tokenize("x²3")?, vec![Token::Identifier(s("x")), op("^"), Token::WholeNumber(s("2")), Token::WholeNumber(s("3"))]);
+        assert_eq!(tokenize("2*−3")?, vec![Token::WholeNumber(s("2")), op("*"), op("-"), Token::WholeNumber(s("3"))]);
+        let spans = |expr| -> Result<Vec<(usize, usize)>, Error> { Ok(super::tokenize(expr)?.into_iter().map(|(_, span)| (span.start, span.end)).collect()) };
+        assert_eq!(spans("2×3")?, vec![(0, 1), (1, 3), (3, 4)]);
+        assert_eq!(spans("π²")?, vec![(0, 2), (2, 4), (2, 4)]);
+        assert_eq!(spans("1<−2")?, vec![(0, 1), (1, 2), (2, 5), (5, 6)]);
+        Ok(())
+    }
+
+    #[test]
+    fn tokenize_unknown() {
+        let message = |input| tokenize(input).unwrap_err().to_string();
+        assert_eq!(message("@`"), "TokenizeError: unknown operator \"@`\"");
+        assert_eq!(message("1 @ 2"), "TokenizeError: unknown operator \"@\"");
+        assert_eq!(message("2*@`+3"), "TokenizeError: unknown operator \"@`\"");
+        assert_eq!(message("a \\ b"), "TokenizeError: unknown operator \"\\\"");
+        assert_eq!(message("é"), "TokenizeError: unknown character 'é'");
+        assert!(matches!(super::tokenize("1 + @`"), Err(Error::TokenizeError(_, Span { start: 4, end: 6 }))));
+        assert!(matches!(super::tokenize("ab é"), Err(Error::TokenizeError(_, Span { start: 3, end: 5 }))));
+    }
+
+    #[test]
+    fn tokenize_with_recovery() {
+        let (tokens, errors) = super::tokenize_recovering("1 @ (é+`");
+        let tokens: Vec<_> = tokens.into_iter().map(|(tok, _)| tok).collect();
+        let s = String::from;
+        assert_eq!(tokens, vec![Token::WholeNumber(s("1")), Token::Space, Token::Invalid(s("@")), Token::Space, Token::Operator(s("(")), Token::Invalid(s("é")), Token::Operator(s("+")), Token::Invalid(s("`"))]);
+        let messages: Vec<_> = errors.iter().map(Error::to_string).collect();
+        assert_eq!(messages, vec!["TokenizeError: unknown operator \"@\"", "TokenizeError: unknown character 'é'", "TokenizeError: unknown operator \"`\""]);
+    }
+
     #[test]
     fn tokenize_space() -> Result<(), Error> {
         assert_eq!(tokenize(" ")?, vec![Token::Space]);
@@ -106,7 +381,7 @@ mod tests {
     #[test]
     fn tokenize_multi() -> Result<(), Error> {
         let s = String::from;
-        assert_eq!(tokenize("3.14  -x2")?, vec![Token::WholeNumber(s("3")), Token::Operator(s(".")), Token::WholeNumber(s("14")), Token::Space, Token::Operator(s("-")), Token::Identifier(s("x2"))]);
+        assert_eq!(tokenize("3.14  -x2")?, vec![Token::Decimal(s("3.14")), Token::Space, Token::Operator(s("-")), Token::Identifier(s("x2"))]);
         Ok(())
     }
 }
@@ -11,12 +11,18 @@ pub struct Prompter {
     history: Vec<String>,
 }
 
+impl Default for Prompter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Prompter {
     pub fn new() -> Self {
         Prompter { prompt: String::from("> "), history: Vec::new() }
     }
 
-    pub fn lines(&mut self) -> LinesIter<impl Write, impl Iterator<Item = Key>> {
+    pub fn lines(&mut self) -> LinesIter<'_, impl Write, impl Iterator<Item = Key>> {
         let terminal = io::stdout().into_raw_mode().expect("termion into_raw_mode error");
         let keys = io::stdin().keys().map(|key| key.expect("termion keys error"));
         LinesIter { prompter: self, terminal, keys }
@@ -43,7 +49,7 @@ fn write<T: Write>(terminal: &mut T, text: &str) {
 
 fn set_input_state<T: Write>(terminal: &mut T, prompt: &str, text: &str, cursor_pos: usize) {
     let clear = clear::CurrentLine;
-    let move_right = cursor::Right((prompt.len() + cursor_pos) as u16);
+    let move_right = cursor::Right((prompt.chars().count() + text[..cursor_pos].chars().count()) as u16);
     write!(terminal, "\r{clear}{prompt}{text}\r{move_right}").expect("write error");
     terminal.flush().expect("flush error");
 }
@@ -59,7 +65,7 @@ mod special_keys {
 }
 
 impl<'a, T: Write, K: Iterator<Item = Key>> Iterator for LinesIter<'a, T, K> {
-    type Item = String;
+    type Item = (usize, String);
 
     fn next(&mut self) -> Option<Self::Item> {
         let mut key_handler = KeyHandler::new(&self.prompter.history);
@@ -73,9 +79,9 @@ impl<'a, T: Write, K: Iterator<Item = Key>> Iterator for LinesIter<'a, T, K> {
                     if line_pos == self.prompter.history.len() {
                         self.prompter.history.push(key_handler.input);
                     }
-                    return Some(self.prompter.history[line_pos].clone());
+                    return Some((line_pos + 1, self.prompter.history[line_pos].clone()));
                 },
-                Key::Char(c) if c.is_ascii() => key_handler.handle_char(c),
+                Key::Char(c) => key_handler.handle_char(c),
                 Key::Backspace => key_handler.handle_backspace(),
                 Key::Left => key_handler.handle_left(),
                 Key::Right => key_handler.handle_right(),
@@ -113,24 +119,32 @@ impl<'a> KeyHandler<'a> {
     fn handle_char(&mut self, c: char) {
         self.prepare_for_edit();
         self.input.insert(self.cursor_pos, c);
-        self.cursor_pos += 1;
+        self.cursor_pos += c.len_utf8();
     }
     fn handle_backspace(&mut self) {
         self.prepare_for_edit();
-        if self.cursor_pos > 0 {
-            self.cursor_pos -= 1;
+        if let Some(c) = self.input[..self.cursor_pos].chars().next_back() {
+            self.cursor_pos -= c.len_utf8();
             self.input.remove(self.cursor_pos);
         }
     }
     fn handle_left(&mut self) {
-        if self.cursor_pos > 0 {
-            self.cursor_pos -= 1;
+        if let Some(c) = self.get_displayed_line()[..self.cursor_pos].chars().next_back() {
+            self.cursor_pos -= c.len_utf8();
         }
     }
     fn handle_right(&mut self) {
-        if self.cursor_pos < self.get_displayed_line().len() {
-            self.cursor_pos += 1;
+        if let Some(c) = self.get_displayed_line()[self.cursor_pos..].chars().next() {
+            self.cursor_pos += c.len_utf8();
+        }
+    }
+    fn fit_cursor_to_line(&mut self) {
+        let line = self.get_displayed_line();
+        let mut pos = self.cursor_pos.min(line.len());
+        while !line.is_char_boundary(pos) {
+            pos -= 1;
         }
+        self.cursor_pos = pos;
     }
     fn handle_up(&mut self) {
         if self.line_pos > 0 {
@@ -140,6 +154,7 @@ impl<'a> KeyHandler<'a> {
             if self.cursor_pos == prev_line_len || self.cursor_pos > curr_line_len {
                 self.cursor_pos = curr_line_len;
             }
+            self.fit_cursor_to_line();
         }
     }
     fn handle_down(&mut self) {
@@ -150,16 +165,17 @@ impl<'a> KeyHandler<'a> {
             if self.cursor_pos == prev_line_len || self.cursor_pos > curr_line_len {
                 self.cursor_pos = curr_line_len;
             }
+            self.fit_cursor_to_line();
         }
     }
     fn handle_word_left(&mut self) {
         let curr_line = self.get_displayed_line();
         let mut in_word = false;
-        for (i, c) in curr_line.char_indices().rev().skip(curr_line.len() - self.cursor_pos) {
+        for (i, c) in curr_line[..self.cursor_pos].char_indices().rev() {
             if c.is_ascii_alphanumeric() {
                 in_word = true;
             } else if in_word {
-                self.cursor_pos = i + 1;
+                self.cursor_pos = i + c.len_utf8();
                 return;
             }
         }
@@ -168,11 +184,11 @@ impl<'a> KeyHandler<'a> {
     fn handle_word_right(&mut self) {
         let curr_line = self.get_displayed_line();
         let mut in_word = false;
-        for (i, c) in curr_line.char_indices().skip(self.cursor_pos) {
+        for (i, c) in curr_line[self.cursor_pos..].char_indices() {
             if c.is_ascii_alphanumeric() {
                 in_word = true;
             } else if in_word {
-                self.cursor_pos = i;
+                self.cursor_pos += i;
                 return;
             }
         }
@@ -187,12 +203,13 @@ impl<'a> KeyHandler<'a> {
     fn handle_word_backspace(&mut self) {
         self.prepare_for_edit();
         let mut in_word = false;
-        for (i, c) in self.input.char_indices().rev().skip(self.input.len() - self.cursor_pos) {
+        for (i, c) in self.input[..self.cursor_pos].char_indices().rev() {
             if c.is_ascii_alphanumeric() {
                 in_word = true;
             } else if in_word {
-                self.input.replace_range(i + 1..self.cursor_pos, "");
-                self.cursor_pos = i + 1;
+                let word_start = i + c.len_utf8();
+                self.input.replace_range(word_start..self.cursor_pos, "");
+                self.cursor_pos = word_start;
                 return;
             }
         }
@@ -213,7 +230,23 @@ mod tests {
     fn do_test(expected_output: Vec<&str>, input: Vec<Key>) {
         let mut prompter = Prompter::new();
         let linesiter = LinesIter { prompter: &mut prompter, terminal: Vec::new(), keys: input.into_iter() };
-        assert_eq!(expected_output, linesiter.collect::<Vec<_>>());
+        assert_eq!(expected_output, linesiter.map(|(_, line)| line).collect::<Vec<_>>());
+    }
+
+    fn do_numbering_test(expected_output: Vec<usize>, input: Vec<Key>) {
+        let mut prompter = Prompter::new();
+        let linesiter = LinesIter { prompter: &mut prompter, terminal: Vec::new(), keys: input.into_iter() };
+        assert_eq!(expected_output, linesiter.map(|(number, _)| number).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn numbering() {
+        use Key::{Char, Up};
+        do_numbering_test(vec![1], vec![Char('a'), Char('\n')]);
+        do_numbering_test(vec![1, 2], vec![Char('a'), Char('\n'), Char('b'), Char('\n')]);
+        do_numbering_test(vec![1, 1], vec![Char('a'), Char('\n'), Up, Char('\n')]);
+        do_numbering_test(vec![1, 2, 1], vec![Char('a'), Char('\n'), Char('b'), Char('\n'), Up, Up, Char('\n')]);
+        do_numbering_test(vec![1, 2], vec![Char('a'), Char('\n'), Up, Char('b'), Char('\n')]);
     }
 
     #[test]
@@ -293,6 +326,23 @@ mod tests {
         do_test(vec!["o"], vec![Char('h'), Char('i'), Char(' '), Char('y'), Char('o'), Left, LINE_BACKSPACE, Char('\n')]);
     }
 
+    #[test]
+    fn unicode() {
+        use special_keys::{LINE_LEFT, WORD_BACKSPACE, WORD_LEFT, WORD_RIGHT};
+        use Key::{Backspace, Char, Down, Left, Right, Up};
+        do_test(vec!["2π"], vec![Char('2'), Char('π'), Char('\n')]);
+        do_test(vec!["3²", "6÷4"], vec![Char('3'), Char('²'), Char('\n'), Char('6'), Char('÷'), Char('4'), Char('\n')]);
+        do_test(vec!["√2"], vec![Char('2'), Left, Char('√'), Char('\n')]);
+        do_test(vec!["π×2"], vec![Char('π'), Char('2'), Left, Char('×'), Right, Char('\n')]);
+        do_test(vec!["π"], vec![Char('π'), Char('×'), Backspace, Char('\n')]);
+        do_test(vec!["2"], vec![Char('π'), Char('2'), Left, Backspace, Char('\n')]);
+        do_test(vec!["÷ b÷ a"], vec![Char('÷'), Char(' '), Char('a'), WORD_LEFT, Char('b'), Char('÷'), Char(' '), Char('\n')]);
+        do_test(vec!["πa÷ b"], vec![Char('π'), Char('a'), Char(' '), Char('b'), LINE_LEFT, Right, WORD_RIGHT, Char('÷'), Char('\n')]);
+        do_test(vec!["π×"], vec![Char('π'), Char('×'), Char('a'), Char('b'), WORD_BACKSPACE, Char('\n')]);
+        do_test(vec!["√√", "a√√"], vec![Char('√'), Char('√'), Char('\n'), Char('a'), Char('b'), Left, Up, Char('a'), Char('\n')]);
+        do_test(vec!["abcd", "√√", "x√√"], vec![Char('a'), Char('b'), Char('c'), Char('d'), Char('\n'), Char('√'), Char('√'), Char('\n'), Up, Up, Left, Left, Down, Char('x'), Char('\n')]);
+    }
+
     #[test]
     fn up_down() {
         use Key::{Backspace, Char, Down, Left, Right, Up};